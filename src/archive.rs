@@ -0,0 +1,100 @@
+use chrono::{Days, Local};
+use colored::*;
+use std::fs;
+
+use crate::commands::print_store_error;
+use crate::model::{get_archive_dir, load_config, Task};
+use crate::store::archive::{archive_year, closed_on, Archive};
+use crate::store::{open_journaled, StoreError, TaskStore};
+
+/// Moves done and cancelled tasks from `store` into the archive. With
+/// `min_age`, only tasks closed at least that many days ago; tasks with no
+/// completion date are then left alone.
+fn move_to_archive(store: &dyn TaskStore, min_age: Option<u32>) -> Result<Vec<Task>, StoreError> {
+    let archive = Archive::new(get_archive_dir());
+    let cutoff = min_age.map(|days| {
+        Local::now()
+            .date_naive()
+            .checked_sub_days(Days::new(days.into()))
+            .unwrap_or_default()
+    });
+
+    store.modify(|data| {
+        let moved: Vec<Task> = data
+            .tasks
+            .iter()
+            .filter(|t| t.status.is_closed())
+            .filter(|t| cutoff.is_none_or(|cutoff| closed_on(t).is_some_and(|day| day <= cutoff)))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return Ok(moved);
+        }
+
+        archive.add(&moved)?;
+        data.tasks.retain(|t| !moved.iter().any(|m| m.id == t.id));
+        Ok(moved)
+    })
+}
+
+pub fn archive_tasks(older_than: Option<u32>) {
+    let store = match open_journaled() {
+        Ok(store) => store.archiving(),
+        Err(e) => {
+            println!("{} {}", "Error opening task store:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    match move_to_archive(&store, older_than) {
+        Ok(moved) if moved.is_empty() => {
+            println!("{}", "No closed tasks to archive".yellow());
+        }
+        Ok(moved) => {
+            let mut years: Vec<i32> = moved.iter().map(archive_year).collect();
+            years.sort();
+            years.dedup();
+            let years: Vec<String> = years.iter().map(|y| y.to_string()).collect();
+
+            println!(
+                "{} {}",
+                format!("✓ Archived {} tasks", moved.len()).green().bold(),
+                format!("(archive/{}.json)", years.join(".json, archive/")).cyan()
+            );
+        }
+        Err(e) => print_store_error("Error archiving tasks:", &e),
+    }
+}
+
+/// Archives tasks closed more than `auto_archive_days` ago, if that is
+/// configured. Runs at most once a day so ordinary commands don't pay for
+/// it; errors are left for the command itself to report. The move is
+/// journaled as "rtask (auto-archive)" and reported on stderr, so it
+/// never mixes into the output of the command that triggered it.
+pub fn auto_archive() {
+    let Some(days) = load_config().auto_archive_days else {
+        return;
+    };
+
+    let marker = get_archive_dir().join(".last-auto-archive");
+    let today = Local::now().date_naive().to_string();
+    if fs::read_to_string(&marker).is_ok_and(|last| last.trim() == today) {
+        return;
+    }
+
+    let Ok(store) = open_journaled() else {
+        return;
+    };
+    let store = store.with_label("rtask (auto-archive)").archiving();
+    if let Ok(moved) = move_to_archive(&store, Some(days)) {
+        if fs::create_dir_all(get_archive_dir()).is_ok() {
+            let _ = fs::write(&marker, &today);
+        }
+        if !moved.is_empty() {
+            eprintln!(
+                "{}",
+                format!("Archived {} tasks closed more than {} days ago", moved.len(), days).cyan()
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use colored::*;
+use dialoguer::Confirm;
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit};
+use crate::model::load_config;
+use crate::store::journal::task_changes;
+use crate::store::{configured_backups, StoreError};
+
+pub fn list_backups() {
+    let backups = configured_backups();
+    let list = match backups.list() {
+        Ok(list) => list,
+        Err(e) => {
+            println!("{} {}", "Error reading backups:".red().bold(), e);
+            return;
+        }
+    };
+
+    if list.is_empty() {
+        let config = load_config();
+        if config.backups == 0 && config.daily_backups == 0 {
+            println!("{}", "Backups are turned off (backups = 0, daily_backups = 0)".yellow());
+        } else {
+            println!("{}", "No backups yet; one is taken before each change".yellow());
+        }
+        return;
+    }
+
+    println!();
+    for (idx, backup) in list.iter().enumerate() {
+        let summary = match backups.read(backup) {
+            Ok(data) => {
+                let open = data.tasks.iter().filter(|t| !t.status.is_closed()).count();
+                format!("{} tasks, {} open", data.tasks.len(), open)
+            }
+            Err(e) => format!("unreadable: {}", e).red().to_string(),
+        };
+        println!(
+            "{:>3}  {}  {}  {}",
+            (idx + 1).to_string().cyan(),
+            backup.taken_at.format("%Y-%m-%d %H:%M:%S").to_string().bright_white(),
+            backup.id.bright_black(),
+            summary
+        );
+    }
+    println!();
+}
+
+/// Shows how backup `id` differs from the current tasks and, once
+/// confirmed, replaces the tasks with it. The restore is itself a change,
+/// so it can be undone and the current tasks are backed up first.
+pub fn restore_backup(id: String) {
+    let backups = configured_backups();
+    let backup = match backups.find(&id) {
+        Ok(Some(backup)) => backup,
+        Ok(None) => {
+            println!("{}", format!("No backup \"{}\"; see `rtask backup list`", id).red());
+            return;
+        }
+        Err(e) => {
+            println!("{} {}", "Error reading backups:".red().bold(), e);
+            return;
+        }
+    };
+
+    let saved = match backups.read(&backup) {
+        Ok(data) => data,
+        Err(e) => {
+            print_store_error("Error reading backup:", &e);
+            return;
+        }
+    };
+    let current = load_or_exit();
+
+    let changes = match task_changes(&current, &saved) {
+        Ok(changes) => changes,
+        Err(e) => {
+            print_store_error("Error comparing backup:", &e);
+            return;
+        }
+    };
+    let series_changed = current.series != saved.series;
+
+    if changes.is_empty() && !series_changed {
+        println!("{}", "The backup matches the current tasks; nothing to restore".yellow());
+        return;
+    }
+
+    println!(
+        "\n{} {}",
+        "Restoring backup from".cyan().bold(),
+        backup.taken_at.format("%Y-%m-%d %H:%M:%S").to_string().bright_white()
+    );
+    for change in &changes {
+        let line = match (&change.before, &change.after) {
+            (Some(task), None) => format!("- [{}] {} (removed)", task.id, task.name).red(),
+            (None, Some(task)) => format!("+ [{}] {} (brought back)", task.id, task.name).green(),
+            (Some(_), Some(task)) => format!(
+                "~ [{}] {}: {}",
+                task.id,
+                task.name,
+                change.changed_fields().join(", ")
+            )
+            .yellow(),
+            (None, None) => continue,
+        };
+        println!("  {}", line);
+    }
+    if series_changed {
+        println!("  {}", "~ recurring series settings".yellow());
+    }
+    println!();
+
+    if !Confirm::new()
+        .with_prompt("Restore this backup?")
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let store = store_or_exit();
+    let result = store.modify(|data| {
+        // IDs are never reused, even for tasks the backup predates.
+        let next_id = data.next_id.max(saved.next_id);
+        *data = saved.clone();
+        data.next_id = next_id;
+        Ok::<_, StoreError>(())
+    });
+
+    match result {
+        Ok(()) => {
+            println!(
+                "{} {}",
+                "✓ Restored backup".green().bold(),
+                backup.id.bright_white()
+            );
+        }
+        Err(e) => print_store_error("Error restoring backup:", &e),
+    }
+}
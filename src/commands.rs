@@ -1,58 +1,111 @@
 use colored::*;
 use dialoguer::{Confirm, Input, Select};
 use std::fs;
-use std::io;
 use std::path::Path;
 use chrono::{Local, NaiveDate};
 
-use crate::model::{Task, SubTask, load_config, get_tasks_file, save_config};
+use crate::model::{
+    Priority, PriorityMatch, PriorityScale, RecurrenceRule, ReopenState, Series, Status, Task, TaskData,
+    default_priority_levels, get_archive_dir, list_names, load_config, save_config, timestamp,
+};
+use crate::query::Query;
+use crate::store::archive::Archive;
+use crate::store::{StoreError, TagFilter, TaskFilter, TaskStore, open_list, open_store};
 
-pub fn load_tasks() -> Vec<Task> {
-    let path = get_tasks_file();
-    
-    if !path.exists() {
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                let _ = fs::create_dir_all(parent);
-            }
+/// Opens the configured task store, or reports why it can't be opened and
+/// exits.
+pub fn store_or_exit() -> Box<dyn TaskStore> {
+    match open_store() {
+        Ok(store) => store,
+        Err(e) => {
+            println!("{} {}", "Error opening task store:".red().bold(), e);
+            std::process::exit(1);
         }
-        return Vec::new();
     }
-    
-    match fs::read_to_string(&path) {
-        Ok(data) => match serde_json::from_str(&data) {
-            Ok(tasks) => tasks,
-            Err(_) => Vec::new(),
-        },
-        Err(_) => Vec::new(),
+}
+
+/// Loads every task, or reports why the store can't be read and exits.
+/// Carrying on with an empty list would let the next save overwrite
+/// whatever is still recoverable in the file.
+pub fn load_or_exit() -> TaskData {
+    match store_or_exit().load() {
+        Ok(data) => data,
+        Err(e) => {
+            println!("{} {}", "Error loading tasks:".red().bold(), e);
+            std::process::exit(1);
+        }
     }
 }
 
-pub fn save_tasks(tasks: &[Task]) -> io::Result<()> {
-    let path = get_tasks_file();
-    
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)?;
+pub fn list_or_exit(filter: &TaskFilter) -> Vec<Task> {
+    match store_or_exit().list(filter) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            println!("{} {}", "Error loading tasks:".red().bold(), e);
+            std::process::exit(1);
         }
     }
-    
-    let json = serde_json::to_string_pretty(&tasks)?;
-    fs::write(path, json)
 }
 
-pub fn add_task(name: String, priority: String, due: Option<String>, tags: Option<String>) {
-    let mut tasks = load_tasks();
+pub fn print_store_error(context: &str, e: &StoreError) {
+    match e {
+        StoreError::NotFound(_) | StoreError::Rejected(_) => println!("{}", e.to_string().red()),
+        _ => println!("{} {}", context.red().bold(), e),
+    }
+}
+
+pub fn validate_due_date(due: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(due.trim(), "%Y-%m-%d")
+        .map(|_| due.trim().to_string())
+        .map_err(|_| "Invalid date format. Use YYYY-MM-DD".to_string())
+}
+
+/// Keys `list` can sort by. Ties fall back to ID order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Priority,
+    Due,
+    Created,
+}
+
+pub fn sort_tasks(tasks: &mut [Task], key: SortKey, scale: &PriorityScale) {
+    match key {
+        SortKey::Id => tasks.sort_by_key(|t| t.id),
+        SortKey::Priority => {
+            tasks.sort_by_key(|t| (scale.rank(&t.priority).unwrap_or(usize::MAX), t.id))
+        }
+        SortKey::Due => tasks.sort_by(|a, b| {
+            // Tasks without a due date go last
+            (a.due_date.is_none(), &a.due_date, a.id).cmp(&(b.due_date.is_none(), &b.due_date, b.id))
+        }),
+        SortKey::Created => tasks.sort_by(|a, b| (&a.created_at, a.id).cmp(&(&b.created_at, b.id))),
+    }
+}
+
+pub fn add_task(
+    name: String,
+    priority: Option<Priority>,
+    due: Option<String>,
+    tags: Option<String>,
+    project: Option<String>,
+    description: Option<String>,
+    repeat: Option<RecurrenceRule>,
+) {
+    let now = chrono::Local::now();
     let due_date = if let Some(due_str) = due {
-        match NaiveDate::parse_from_str(&due_str, "%Y-%m-%d") {
-            Ok(_) => Some(due_str),
-            Err(_) => {
-                println!("{}", "Invalid date format. Use YYYY-MM-DD".red().bold());
+        match validate_due_date(&due_str) {
+            Ok(due) => Some(due),
+            Err(e) => {
+                println!("{}", e.red().bold());
                 return;
             }
         }
     } else {
-        None
+        repeat
+            .as_ref()
+            .map(|rule| rule.first_due(now.date_naive()).format("%Y-%m-%d").to_string())
     };
     let tags_vec = tags
         .unwrap_or_default()
@@ -60,28 +113,52 @@ pub fn add_task(name: String, priority: String, due: Option<String>, tags: Optio
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
         .collect::<Vec<String>>();
-    let now = chrono::Local::now();
-    let task = Task {
-        id: tasks.len() + 1,
+    let mut task = Task {
+        id: 0,
+        uuid: None,
         name,
-        priority: priority.to_lowercase(),
-        status: "pending".to_string(),
+        priority: priority.unwrap_or_else(|| {
+            PriorityScale::load().default_priority(&load_config())
+        }),
+        status: Status::Pending,
         progress: 0,
         due_date,
         tags: tags_vec,
+        project,
         created_at: now.format("%Y-%m-%d %H:%M").to_string(),
         completed_at: None,
         subtasks: Vec::new(),
+        description: description.filter(|d| !d.trim().is_empty()),
+        annotations: Vec::new(),
+        series: None,
+        depends_on: Vec::new(),
+        parent: None,
+        reopen: None,
     };
     
-    tasks.push(task.clone());
+    let result = match repeat {
+        None => store_or_exit().insert(task),
+        Some(rule) => {
+            let series = Series::from_task(&task, rule);
+            task.series = Some(series.uuid.clone());
+            
+            store_or_exit().modify(|data| {
+                task.id = data.allocate_id();
+                task.uuid = Some(crate::model::new_uuid());
+                data.tasks.push(task.clone());
+                data.series.push(series);
+                Ok(task)
+            })
+        }
+    };
     
-    match save_tasks(&tasks) {
-        Ok(_) => {
+    match result {
+        Ok(task) => {
             println!(
-                "{} {}",
+                "{} {} {}",
                 "✓ Task added:".green().bold(),
-                task.name.bright_white()
+                task.name.bright_white(),
+                format!("(ID {})", task.id).cyan()
             );
         }
         Err(e) => {
@@ -90,20 +167,27 @@ pub fn add_task(name: String, priority: String, due: Option<String>, tags: Optio
     }
 }
 
-pub fn format_priority(priority: &str) -> String {
-    match priority.to_lowercase().as_str() {
-        "high" => priority.bright_red().to_string(),
-        "medium" => priority.yellow().to_string(),
-        "low" => priority.green().to_string(),
-        _ => priority.normal().to_string(),
+/// Colours a priority by its place in the scale: the most urgent level is
+/// red, the least urgent green, everything in between yellow.
+pub fn format_priority(priority: &Priority, scale: &PriorityScale) -> String {
+    let text = priority.as_str();
+    match scale.rank(priority) {
+        Some(0) => text.bright_red().to_string(),
+        Some(rank) if rank + 1 == scale.levels().len() => text.green().to_string(),
+        Some(_) => text.yellow().to_string(),
+        None => text.normal().to_string(),
     }
 }
 
-pub fn format_status(status: &str) -> String {
-    match status.to_lowercase().as_str() {
-        "done" => status.bright_green().to_string(),
-        "pending" => status.yellow().to_string(),
-        _ => status.normal().to_string(),
+pub fn format_status(status: Status) -> String {
+    let text = status.as_str();
+    match status {
+        Status::Pending => text.yellow().to_string(),
+        Status::InProgress => text.bright_cyan().to_string(),
+        Status::Blocked => text.bright_red().to_string(),
+        Status::Waiting => text.magenta().to_string(),
+        Status::Done => text.bright_green().to_string(),
+        Status::Cancelled => text.bright_black().to_string(),
     }
 }
 
@@ -118,174 +202,494 @@ pub fn format_progress_bar(progress: u8) -> String {
     format!("[{}{}] {}%", filled_chars.green(), empty_chars, progress)
 }
 
-pub fn list_tasks(filter: Option<String>, all: bool, completed: bool) {
-    let tasks = load_tasks();
-    if tasks.is_empty() {
-        println!("{}", "No tasks found".yellow());
+/// Prints one task per line. `blocked_by` lists the open prerequisites the
+/// task is waiting on, if any.
+pub fn print_task_line(task: &Task, scale: &PriorityScale, blocked_by: &[usize]) {
+    let id_str = format!("[{}]", task.id).cyan().bold();
+    let priority_str = format!("[{}]", format_priority(&task.priority, scale));
+    let status_str = format!("[{}]", format_status(task.status));
+    let progress_str = format_progress_bar(task.progress);
+    
+    let due_str = if let Some(due) = &task.due_date {
+        format!("(Due: {})", due).yellow()
+    } else {
+        "".normal()
+    };
+    
+    let project_str = match &task.project {
+        Some(project) => format!("@{}", project).green(),
+        None => "".normal(),
+    };
+    
+    let tags_str = if !task.tags.is_empty() {
+        format!("#{}",  task.tags.join(" #")).bright_blue()
+    } else {
+        "".normal()
+    };
+    
+    let subtasks = task.all_subtasks();
+    let subtasks_str = if !subtasks.is_empty() {
+        let completed = subtasks.iter().filter(|s| s.completed).count();
+        format!("[{}/{}]", completed, subtasks.len()).bright_magenta()
+    } else {
+        "".normal()
+    };
+    
+    let blocked_str = if !blocked_by.is_empty() {
+        format!("⛓ blocked by {}", crate::deps::format_ids(blocked_by)).red()
+    } else {
+        "".normal()
+    };
+    
+    println!("{} {} {} {} {} {} {} {} {} {}", 
+        id_str,
+        priority_str,
+        status_str,
+        progress_str,
+        task.name.bright_white(),
+        due_str,
+        project_str,
+        tags_str,
+        subtasks_str,
+        blocked_str
+    );
+}
+
+/// What `rtask list` shows. Every field left at its default shows the open
+/// tasks in ID order.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Tag terms, as [`TagFilter::parse`] reads them.
+    pub filter: Vec<String>,
+    /// Keeps tasks in this project and the projects below it.
+    pub project: Option<String>,
+    pub all: bool,
+    pub completed: bool,
+    pub status: Option<Status>,
+    pub priority: Option<PriorityMatch>,
+    pub sort: SortKey,
+    pub search: Option<String>,
+    /// List archived tasks instead of the active ones.
+    pub archived: bool,
+    /// List tasks from every list, not just the active one.
+    pub all_lists: bool,
+    /// Further conditions in the [query language](crate::query).
+    pub query: Query,
+}
+
+pub fn list_tasks(options: ListOptions) {
+    let ListOptions { filter, project, all, completed, status, priority, sort, search, archived, all_lists, query } = options;
+    let scale = PriorityScale::load();
+    let task_filter = TaskFilter {
+        closed: if completed || all || archived || status.is_some() || query.selects_closed() {
+            None
+        } else {
+            Some(false)
+        },
+        status: if completed { Some(Status::Done) } else { status },
+        priorities: priority.map(|p| p.levels(&scale)),
+        tags: TagFilter::parse(&filter),
+        project,
+        search,
+        ..TaskFilter::default()
+    };
+    
+    if archived {
+        list_archived_tasks(&task_filter, &query, sort, &scale);
+        return;
+    }
+    if all_lists {
+        list_tasks_across_lists(&task_filter, &query, sort, &scale);
         return;
     }
-    let filtered_tasks: Vec<Task> = tasks
-        .into_iter()
-        .filter(|task| {
-            let status_match = if completed {
-                task.status == "done"
-            } else if all {
-                true
-            } else {
-                task.status != "done"
-            };
-            
-            let tag_match = if let Some(tag_filter) = &filter {
-                task.tags.iter().any(|tag| tag.contains(tag_filter))
-            } else {
-                true
-            };
-            
-            status_match && tag_match
-        })
-        .collect();
     
+    let mut filtered_tasks = list_or_exit(&task_filter);
+    if !query.is_empty() {
+        let data = load_or_exit();
+        filtered_tasks.retain(|t| query.matches(t, &data));
+    }
+    sort_tasks(&mut filtered_tasks, sort, &scale);
+    
+    println!("{} {}", "Store:".cyan().bold(), crate::lists::active_store_label().bright_black());
     if filtered_tasks.is_empty() {
-        println!("{}", "No matching tasks found".yellow());
+        let any_tasks = !load_or_exit().tasks.is_empty();
+        if any_tasks {
+            println!("{}", "No matching tasks found".yellow());
+        } else {
+            println!("{}", "No tasks found".yellow());
+        }
         return;
     }
-    let colored_tasks: Vec<Task> = filtered_tasks
+    let blocked = crate::deps::blocked_tasks(&filtered_tasks);
+    println!();
+    for task in &filtered_tasks {
+        print_task_line(task, &scale, blocked.get(&task.id).map_or(&[], Vec::as_slice));
+    }
+    println!();
+    
+    let counts: Vec<String> = Status::ALL
         .iter()
-        .map(|task| {
-            let mut colored_task = task.clone();
-            colored_task.priority = format_priority(&task.priority);
-            colored_task.status = format_status(&task.status);
-            colored_task
+        .filter_map(|&status| {
+            let count = filtered_tasks.iter().filter(|t| t.status == status).count();
+            (count > 0).then(|| format!("{} {}", count, format_status(status)))
         })
         .collect();
+    
+    println!("\n{} {}", "Summary:".cyan().bold(), counts.join(" "));
+}
+
+/// Lists archived tasks matching `filter` and `query`. Tasks that are also
+/// in the active store (e.g. after an undo) are shown there instead.
+fn list_archived_tasks(filter: &TaskFilter, query: &Query, sort: SortKey, scale: &PriorityScale) {
+    let archive = Archive::new(get_archive_dir());
+    let mut tasks = match archive.list(filter) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            print_store_error("Error reading the archive:", &e);
+            return;
+        }
+    };
+    let active = load_or_exit();
+    tasks.retain(|t| active.find(t.id).is_none() && query.matches(t, &active));
+    sort_tasks(&mut tasks, sort, scale);
+    
+    if tasks.is_empty() {
+        println!("{}", "No matching archived tasks".yellow());
+        return;
+    }
     println!();
-    for task in &colored_tasks {
-        let id_str = format!("[{}]", task.id).cyan().bold();
-        let priority_str = format!("[{}]", task.priority);
-        let status_str = format!("[{}]", task.status);
-        let progress_str = format_progress_bar(task.progress);
-        
-        let due_str = if let Some(due) = &task.due_date {
-            format!("(Due: {})", due).yellow()
-        } else {
-            "".normal()
-        };
-        
-        let tags_str = if !task.tags.is_empty() {
-            format!("#{}",  task.tags.join(" #")).bright_blue()
-        } else {
-            "".normal()
-        };
-        
-        let subtasks_str = if !task.subtasks.is_empty() {
-            let completed = task.subtasks.iter().filter(|s| s.completed).count();
-            format!("[{}/{}]", completed, task.subtasks.len()).bright_magenta()
-        } else {
-            "".normal()
-        };
-        
-        println!("{} {} {} {} {} {} {} {}", 
-            id_str,
-            priority_str,
-            status_str,
-            progress_str,
-            task.name.bright_white(),
-            due_str,
-            tags_str,
-            subtasks_str
-        );
+    for task in &tasks {
+        print_task_line(task, scale, &[]);
+    }
+    println!("\n{} {}", "Archived:".cyan().bold(), tasks.len());
+}
+
+/// Lists the tasks matching `filter` and `query` from every list, grouped
+/// by list, with the list's name in front of each task.
+fn list_tasks_across_lists(filter: &TaskFilter, query: &Query, sort: SortKey, scale: &PriorityScale) {
+    let mut lists = Vec::new();
+    for name in list_names() {
+        match open_list(&name).and_then(|store| store.load()) {
+            Ok(data) => {
+                let mut tasks: Vec<Task> = data
+                    .tasks
+                    .iter()
+                    .filter(|t| filter.matches(t) && query.matches(t, &data))
+                    .cloned()
+                    .collect();
+                sort_tasks(&mut tasks, sort, scale);
+                lists.push((name, data, tasks));
+            }
+            Err(e) => print_store_error(&format!("Error loading list '{}':", name), &e),
+        }
+    }
+    
+    let width = lists.iter().map(|(name, _, _)| name.len()).max().unwrap_or(0);
+    let total: usize = lists.iter().map(|(_, _, tasks)| tasks.len()).sum();
+    if total == 0 {
+        println!("{}", "No matching tasks in any list".yellow());
+        return;
+    }
+    
+    println!("{} {}", "Store:".cyan().bold(), crate::lists::store_label().bright_black());
+    println!();
+    for (name, data, tasks) in &lists {
+        for task in tasks {
+            let blocked_by = if task.status.is_closed() {
+                Vec::new()
+            } else {
+                crate::deps::open_prerequisites(data, task)
+            };
+            print!("{} ", format!("{:<width$}", name, width = width).magenta());
+            print_task_line(task, scale, &blocked_by);
+        }
     }
     println!();
-    let pending_count = filtered_tasks
+    
+    let counts: Vec<String> = lists
         .iter()
-        .filter(|t| t.status == "pending")
-        .count();
+        .filter(|(_, _, tasks)| !tasks.is_empty())
+        .map(|(name, _, tasks)| format!("{} {}", tasks.len(), name))
+        .collect();
+    println!("\n{} {}", "Summary:".cyan().bold(), counts.join(", "));
+}
+
+/// What completing one task did, for reporting afterwards.
+pub(crate) struct Completion {
+    name: String,
+    next: Option<Task>,
+    unfinished: Vec<usize>,
+    dependents: Vec<usize>,
+    unblocked: Vec<usize>,
+}
+
+/// Completes task `id` inside a transaction. Returns `None` if it was
+/// already done.
+fn complete_in(data: &mut TaskData, id: usize) -> Result<Option<Completion>, StoreError> {
+    let task = data.find(id).ok_or(StoreError::NotFound(id))?;
+    if task.status == Status::Done {
+        return Ok(None);
+    }
+    let unfinished = crate::deps::open_prerequisites(data, task);
+    
+    let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    task.complete();
+    let name = task.name.clone();
     
-    let done_count = filtered_tasks.iter().filter(|t| t.status == "done").count();
+    let dependents = crate::deps::dependents(data, id);
+    let unblocked: Vec<usize> = dependents
+        .iter()
+        .copied()
+        .filter(|dep| {
+            data.find(*dep).is_some_and(|t| {
+                !t.status.is_closed() && crate::deps::open_prerequisites(data, t).is_empty()
+            })
+        })
+        .collect();
     
+    let next = crate::recur::spawn_next(data, id);
+    Ok(Some(Completion { name, next, unfinished, dependents, unblocked }))
+}
+
+/// Rolls subtask changes on task `id` up into it. If the change checked
+/// off its last open subtask, the task is completed through
+/// [`complete_in`], and a reopen later goes back to `before`.
+pub(crate) fn settle_subtasks_in(
+    data: &mut TaskData,
+    id: usize,
+    before: ReopenState,
+) -> Result<Option<Completion>, StoreError> {
+    let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    if !task.settle_subtasks() {
+        return Ok(None);
+    }
+    let completion = complete_in(data, id)?;
+    if let Some(task) = data.find_mut(id) {
+        task.reopen = Some(before);
+    }
+    Ok(completion)
+}
+
+pub(crate) fn print_completion(completion: Completion) {
+    let Completion { name, next, unfinished, dependents, unblocked } = completion;
     println!(
-        "\n{} {} {}",
-        "Summary:".cyan().bold(),
-        format!("{} pending", pending_count).yellow(),
-        format!("{} completed", done_count).green()
+        "{} {}",
+        "✓ Completed task:".green().bold(),
+        name.bright_white()
     );
+    if !unfinished.is_empty() {
+        println!(
+            "{} {}",
+            "⚠ Completed before its prerequisites:".yellow().bold(),
+            crate::deps::format_ids(&unfinished).yellow()
+        );
+        if !dependents.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "Tasks waiting on it ({}) may now look ready too early",
+                    crate::deps::format_ids(&dependents)
+                )
+                .yellow()
+            );
+        }
+    }
+    if !unblocked.is_empty() {
+        println!(
+            "{} {}",
+            "Unblocked:".cyan().bold(),
+            crate::deps::format_ids(&unblocked).bright_white()
+        );
+    }
+    if let Some(next) = next {
+        println!(
+            "{} {}",
+            "↻ Next occurrence:".cyan().bold(),
+            format!(
+                "ID {}, due {}",
+                next.id,
+                next.due_date.unwrap_or_default()
+            )
+            .bright_white()
+        );
+    }
 }
 
 pub fn complete_task(id: usize) {
-    let mut tasks = load_tasks();
+    complete_tasks(vec![id]);
+}
+
+/// Completes every task in `ids` as one change, so a single `rtask undo`
+/// reverts all of them.
+pub fn complete_tasks(ids: Vec<usize>) {
+    let store = store_or_exit();
     
-    let task_idx = tasks.iter().position(|t| t.id == id);
+    let result = store.modify(|data| {
+        ids.iter().map(|id| complete_in(data, *id)).collect::<Result<Vec<_>, _>>()
+    });
     
-    match task_idx {
-        Some(idx) => {
-            if tasks[idx].status == "done" {
-                println!("{}", "Task is already completed".yellow());
-                return;
-            }
-            tasks[idx].status = "done".to_string();
-            tasks[idx].progress = 100;
-            tasks[idx].completed_at = Some(Local::now().format("%Y-%m-%d %H:%M").to_string());
-            
-            for subtask in &mut tasks[idx].subtasks {
-                subtask.completed = true;
-            }
-            
-            match save_tasks(&tasks) {
-                Ok(_) => {
-                    println!(
-                        "{} {}",
-                        "✓ Completed task:".green().bold(),
-                        tasks[idx].name.bright_white()
-                    );
-                }
-                Err(e) => {
-                    println!("{} {}", "Error completing task:".red().bold(), e);
+    match result {
+        Ok(completions) => {
+            let total = completions.len();
+            let mut completed = 0;
+            for completion in completions {
+                match completion {
+                    Some(completion) => {
+                        completed += 1;
+                        print_completion(completion);
+                    }
+                    None if total == 1 => println!("{}", "Task is already completed".yellow()),
+                    None => {}
                 }
             }
+            if total > 1 {
+                let summary = if completed == total {
+                    format!("Completed {} tasks", total)
+                } else {
+                    format!("Completed {} of {} tasks; the rest were already done", completed, total)
+                };
+                println!("{}", summary.cyan().bold());
+            }
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+        Err(e) => print_store_error("Error completing task:", &e),
+    }
+}
+
+/// Moves a task to any status other than done, which goes through
+/// [`complete_task`] so progress and subtasks are closed out too.
+pub fn set_task_status(id: usize, status: Status) {
+    if status == Status::Done {
+        complete_task(id);
+        return;
+    }
+    
+    let store = store_or_exit();
+    
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        if task.status == status {
+            return Ok(None);
+        }
+        if !status.is_closed() {
+            task.reopen();
         }
+        task.status = status;
+        task.completed_at = if status.is_closed() {
+            Some(timestamp())
+        } else {
+            None
+        };
+        Ok(Some(task.name.clone()))
+    });
+    
+    match result {
+        Ok(Some(name)) => {
+            println!(
+                "{} {} {}",
+                "✓ Task".green().bold(),
+                name.bright_white(),
+                format!("is now {}", format_status(status)).green()
+            );
+        }
+        Ok(None) => {
+            println!("{}", format!("Task is already {}", status).yellow());
+        }
+        Err(e) => print_store_error("Error updating task status:", &e),
+    }
+}
+
+/// Takes a done or cancelled task back to open work with the progress
+/// and subtasks it had before it was closed.
+pub fn reopen_task(id: usize) {
+    let store = store_or_exit();
+    
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        if !task.reopen() {
+            return Ok(None);
+        }
+        Ok(Some((task.name.clone(), task.status, task.progress)))
+    });
+    
+    match result {
+        Ok(Some((name, status, progress))) => {
+            println!(
+                "{} {} {}",
+                "✓ Reopened task:".green().bold(),
+                name.bright_white(),
+                format!("({}, {}%)", status, progress).cyan()
+            );
+        }
+        Ok(None) => {
+            println!("{}", "Task is not completed or cancelled".yellow());
+        }
+        Err(e) => print_store_error("Error reopening task:", &e),
     }
 }
 
 pub fn remove_task(id: usize) {
-    let mut tasks = load_tasks();
+    let store = store_or_exit();
     
-    let task_idx = tasks.iter().position(|t| t.id == id);
+    let task = match store.get(id) {
+        Ok(Some(task)) => task,
+        Ok(None) => {
+            println!("{}", format!("Task with ID {} not found", id).red());
+            return;
+        }
+        Err(e) => {
+            println!("{} {}", "Error removing task:".red().bold(), e);
+            return;
+        }
+    };
     
-    match task_idx {
-        Some(idx) => {
-            let task_name = tasks[idx].name.clone();
-            
-            if Confirm::new()
-                .with_prompt(format!("Remove task \"{}\"?", task_name))
-                .default(false)
-                .interact()
-                .unwrap_or(false)
-            {
-                tasks.remove(idx);
-                for (i, task) in tasks.iter_mut().enumerate() {
-                    task.id = i + 1;
-                }
-                
-                match save_tasks(&tasks) {
-                    Ok(_) => {
-                        println!(
-                            "{} {}",
-                            "✓ Removed task:".green().bold(),
-                            task_name.bright_white()
-                        );
-                    }
-                    Err(e) => {
-                        println!("{} {}", "Error removing task:".red().bold(), e);
-                    }
-                }
+    if Confirm::new()
+        .with_prompt(format!("Remove task \"{}\"?", task.name))
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+    {
+        match store.delete(id) {
+            Ok(Some(_)) => {
+                println!(
+                    "{} {}",
+                    "✓ Removed task:".green().bold(),
+                    task.name.bright_white()
+                );
+            }
+            Ok(None) => {
+                println!("{}", format!("Task with ID {} not found", id).red());
+            }
+            Err(e) => {
+                println!("{} {}", "Error removing task:".red().bold(), e);
             }
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+    }
+}
+
+/// Removes several tasks at once, without asking about each; the caller
+/// has already confirmed the whole set.
+pub fn remove_tasks(ids: Vec<usize>) {
+    let store = store_or_exit();
+    
+    let result = store.modify(|data| {
+        let mut removed = Vec::new();
+        for id in &ids {
+            removed.push(data.remove(*id).ok_or(StoreError::NotFound(*id))?);
+        }
+        Ok(removed)
+    });
+    
+    match result {
+        Ok(removed) => {
+            for task in removed {
+                println!(
+                    "{} {}",
+                    "✓ Removed task:".green().bold(),
+                    task.name.bright_white()
+                );
+            }
         }
+        Err(e) => print_store_error("Error removing tasks:", &e),
     }
 }
 
@@ -306,22 +710,32 @@ pub fn setup_config() {
         .interact_text()
         .unwrap();
     
-    let priorities = vec!["low", "medium", "high"];
-    let default_priority_idx = priorities
-        .iter()
-        .position(|&p| p == config.default_priority)
-        .unwrap_or(1);
+    let current_scale = config.priorities.join(", ");
+    let mut scales = vec![default_priority_levels(), (0..5).map(|n| format!("P{}", n)).collect()];
+    if !scales.contains(&config.priorities) {
+        scales.push(config.priorities.clone());
+    }
+    let scale_names: Vec<String> = scales.iter().map(|levels| levels.join(", ")).collect();
+    
+    let scale_idx = Select::new()
+        .with_prompt("Priority levels (most urgent first):")
+        .items(&scale_names)
+        .default(scale_names.iter().position(|s| *s == current_scale).unwrap_or(0))
+        .interact()
+        .unwrap();
+    let scale = PriorityScale::new(scales[scale_idx].clone());
     
     let default_priority_idx = Select::new()
         .with_prompt("Choose default priority:")
-        .items(&priorities)
-        .default(default_priority_idx)
+        .items(scale.levels())
+        .default(scale.rank(&scale.default_priority(&config)).unwrap_or(0))
         .interact()
         .unwrap();
     
     config.tasks_dir = tasks_dir;
     config.user_name = user_name;
-    config.default_priority = priorities[default_priority_idx].to_string();
+    config.priorities = scale.levels().to_vec();
+    config.default_priority = scale.levels()[default_priority_idx].clone();
     
     match save_config(&config) {
         Ok(_) => {
@@ -332,7 +746,7 @@ pub fn setup_config() {
                     println!(
                         "{} {}",
                         "Error creating tasks directory:".red().bold(),
-                        e.to_string()
+                        e
                     );
                 } else {
                     println!("{}", "✓ Created tasks directory".green());
@@ -356,6 +770,9 @@ pub fn interactive_mode() {
             "Today's tasks",
             "Complete a task",
             "Update task progress",
+            "Change task status",
+            "Modify a task",
+            "Annotate a task",
             "Manage subtasks",
             "Remove a task",
             "Show task details",
@@ -372,14 +789,17 @@ pub fn interactive_mode() {
         match selection {
             Ok(0) => interactive_add_task(),
             Ok(1) => interactive_list_tasks(),
-            Ok(2) => show_today_tasks(),
+            Ok(2) => show_today_tasks(Query::default()),
             Ok(3) => interactive_complete_task(),
             Ok(4) => interactive_update_progress(),
-            Ok(5) => crate::subtasks::interactive_manage_subtasks(),
-            Ok(6) => interactive_remove_task(),
-            Ok(7) => interactive_show_task(),
-            Ok(8) => setup_config(),
-            Ok(9) | _ => break,
+            Ok(5) => interactive_set_status(),
+            Ok(6) => crate::modify::interactive_modify_task(),
+            Ok(7) => crate::notes::interactive_annotate_task(),
+            Ok(8) => crate::subtasks::interactive_manage_subtasks(),
+            Ok(9) => interactive_remove_task(),
+            Ok(10) => interactive_show_task(),
+            Ok(11) => setup_config(),
+            _ => break,
         }
     }
     
@@ -392,21 +812,18 @@ pub fn interactive_add_task() {
         .interact_text()
         .unwrap();
     
-    let priorities = vec!["low", "medium", "high"];
     let config = load_config();
-    let default_priority_idx = priorities
-        .iter()
-        .position(|&p| p == config.default_priority)
-        .unwrap_or(1);
+    let scale = PriorityScale::new(config.priorities.clone());
+    let default_priority = scale.default_priority(&config);
     
     let priority_idx = Select::new()
         .with_prompt("Priority")
-        .items(&priorities)
-        .default(default_priority_idx)
+        .items(scale.levels())
+        .default(scale.rank(&default_priority).unwrap_or(0))
         .interact()
         .unwrap();
     
-    let priority = priorities[priority_idx].to_string();
+    let priority = scale.parse(&scale.levels()[priority_idx]).ok();
     
     let has_due_date = Confirm::new()
         .with_prompt("Set a due date?")
@@ -440,7 +857,7 @@ pub fn interactive_add_task() {
         None
     };
     
-    add_task(name, priority, due_date, tags);
+    add_task(name, priority, due_date, tags, None, None, None);
     
     let add_subtasks = Confirm::new()
         .with_prompt("Add subtasks now?")
@@ -449,7 +866,7 @@ pub fn interactive_add_task() {
         .unwrap();
     
     if add_subtasks {
-        let tasks = load_tasks();
+        let tasks = load_or_exit().tasks;
         if let Some(task) = tasks.last() {
             crate::subtasks::interactive_add_subtasks(task.id);
         }
@@ -473,25 +890,26 @@ pub fn interactive_list_tasks() {
     
     let filter = if has_filter {
         let filter_input: String = Input::new()
-            .with_prompt("Tag filter")
+            .with_prompt("Tags (e.g. backend -someday)")
             .interact_text()
             .unwrap();
-        Some(filter_input)
+        filter_input.split_whitespace().map(str::to_string).collect()
     } else {
-        None
+        Vec::new()
     };
     
+    let options = ListOptions { filter, ..ListOptions::default() };
     match selection {
-        0 => list_tasks(filter, true, false),
-        1 => list_tasks(filter, false, false),
-        2 => list_tasks(filter, false, true),
+        0 => list_tasks(ListOptions { all: true, ..options }),
+        1 => list_tasks(options),
+        2 => list_tasks(ListOptions { completed: true, ..options }),
         _ => {}
     }
 }
 
 pub fn interactive_complete_task() {
-    let tasks = load_tasks();
-    let pending_tasks: Vec<&Task> = tasks.iter().filter(|t| t.status == "pending").collect();
+    let tasks = load_or_exit().tasks;
+    let pending_tasks: Vec<&Task> = tasks.iter().filter(|t| !t.status.is_closed()).collect();
     
     if pending_tasks.is_empty() {
         println!("{}", "No pending tasks to complete".yellow());
@@ -508,17 +926,48 @@ pub fn interactive_complete_task() {
         .items(&task_names)
         .interact();
     
-    match selection {
-        Ok(idx) => {
-            let task_id = pending_tasks[idx].id;
-            complete_task(task_id);
+    if let Ok(idx) = selection {
+        let task_id = pending_tasks[idx].id;
+        complete_task(task_id);
+    }
+}
+
+pub fn interactive_set_status() {
+    let tasks = load_or_exit().tasks;
+    
+    if tasks.is_empty() {
+        println!("{}", "No tasks found".yellow());
+        return;
+    }
+    
+    let task_names: Vec<String> = tasks
+        .iter()
+        .map(|t| format!("[{}] {} ({})", t.id, t.name, t.status))
+        .collect();
+    
+    let selection = Select::new()
+        .with_prompt("Select task")
+        .items(&task_names)
+        .interact();
+    
+    if let Ok(idx) = selection {
+        let task = &tasks[idx];
+        let current = Status::ALL.iter().position(|&s| s == task.status).unwrap_or(0);
+        
+        let status_idx = Select::new()
+            .with_prompt("New status")
+            .items(&Status::ALL)
+            .default(current)
+            .interact();
+        
+        if let Ok(status_idx) = status_idx {
+            set_task_status(task.id, Status::ALL[status_idx]);
         }
-        Err(_) => {}
     }
 }
 
 pub fn interactive_remove_task() {
-    let tasks = load_tasks();
+    let tasks = load_or_exit().tasks;
     
     if tasks.is_empty() {
         println!("{}", "No tasks to remove".yellow());
@@ -535,33 +984,79 @@ pub fn interactive_remove_task() {
         .items(&task_names)
         .interact();
     
-    match selection {
-        Ok(idx) => {
-            let task_id = tasks[idx].id;
-            remove_task(task_id);
-        }
-        Err(_) => {}
+    if let Ok(idx) = selection {
+        let task_id = tasks[idx].id;
+        remove_task(task_id);
     }
 }
 
 pub fn show_task_details(id: usize) {
-    let tasks = load_tasks();
-    let task = tasks.iter().find(|t| t.id == id);
+    let mut data = load_or_exit();
+    let mut archived = false;
+    if data.find(id).is_none() {
+        if let Ok(Some(task)) = Archive::new(get_archive_dir()).find(id) {
+            data.tasks.push(task);
+            archived = true;
+        }
+    }
+    let task = data.find(id);
     
     match task {
         Some(task) => {
             println!("{}", "Task Details".cyan().bold());
             println!("{}", "------------".cyan());
             println!("{}: {}", "ID".yellow(), task.id);
+            if let Some(uuid) = &task.uuid {
+                println!("{}: {}", "UUID".yellow(), uuid);
+            }
             println!("{}: {}", "Name".yellow(), task.name);
-            println!("{}: {}", "Priority".yellow(), format_priority(&task.priority));
-            println!("{}: {}", "Status".yellow(), format_status(&task.status));
+            println!("{}: {}", "Priority".yellow(), format_priority(&task.priority, &PriorityScale::load()));
+            if archived {
+                println!("{}: {} {}", "Status".yellow(), format_status(task.status), "(archived)".bright_black());
+            } else {
+                println!("{}: {}", "Status".yellow(), format_status(task.status));
+            }
             println!("{}: {}", "Progress".yellow(), format_progress_bar(task.progress));
             
             if let Some(due) = &task.due_date {
                 println!("{}: {}", "Due Date".yellow(), due);
             }
             
+            if let Some(parent) = task.parent {
+                match data.find(parent) {
+                    Some(parent) => println!("{}: [{}] {}", "Parent".yellow(), parent.id, parent.name),
+                    None => println!("{}: [{}] {}", "Parent".yellow(), parent, "(archived)".bright_black()),
+                }
+            }
+            
+            if let Some(series) = task.series.as_deref().and_then(|uuid| data.find_series(uuid)) {
+                println!("{}: {}", "Repeats".yellow(), series.rule);
+            }
+            
+            if !task.depends_on.is_empty() {
+                let prerequisites: Vec<String> = task
+                    .depends_on
+                    .iter()
+                    .map(|id| match data.find(*id) {
+                        Some(dep) => {
+                            let mark = if dep.status.is_closed() { "✓".green() } else { "✗".red() };
+                            format!("{} [{}] {}", mark, dep.id, dep.name)
+                        }
+                        None => format!("{} [{}] {}", "✓".green(), id, "(archived)".bright_black()),
+                    })
+                    .collect();
+                println!("{}: {}", "Depends on".yellow(), prerequisites.join(", "));
+            }
+            
+            let dependents = crate::deps::dependents(&data, task.id);
+            if !dependents.is_empty() {
+                println!("{}: {}", "Blocks".yellow(), crate::deps::format_ids(&dependents));
+            }
+            
+            if let Some(project) = &task.project {
+                println!("{}: {}", "Project".yellow(), project);
+            }
+            
             if !task.tags.is_empty() {
                 println!("{}: {}", "Tags".yellow(), task.tags.join(", "));
             }
@@ -572,16 +1067,26 @@ pub fn show_task_details(id: usize) {
                 println!("{}: {}", "Completed".yellow(), completed);
             }
             
+            if let Some(description) = &task.description {
+                println!("\n{}", "Description:".cyan().bold());
+                for line in description.lines() {
+                    println!("  {}", line);
+                }
+            }
+            
+            if !task.annotations.is_empty() {
+                let mut annotations: Vec<_> = task.annotations.iter().collect();
+                annotations.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+                
+                println!("\n{}", "Annotations:".cyan().bold());
+                for annotation in annotations {
+                    println!("{} {}", annotation.created_at.bright_black(), annotation.text);
+                }
+            }
+            
             if !task.subtasks.is_empty() {
                 println!("\n{}", "Subtasks:".cyan().bold());
-                for (i, subtask) in task.subtasks.iter().enumerate() {
-                    let status = if subtask.completed {
-                        "[✓]".green()
-                    } else {
-                        "[ ]".yellow()
-                    };
-                    println!("{} {}: {}", status, (i + 1).to_string().cyan(), subtask.name);
-                }
+                crate::subtasks::print_subtask_tree(task, &PriorityScale::load());
             }
         },
         None => {
@@ -591,7 +1096,7 @@ pub fn show_task_details(id: usize) {
 }
 
 pub fn interactive_show_task() {
-    let tasks = load_tasks();
+    let tasks = load_or_exit().tasks;
     
     if tasks.is_empty() {
         println!("{}", "No tasks found".yellow());
@@ -608,26 +1113,26 @@ pub fn interactive_show_task() {
         .items(&task_names)
         .interact();
     
-    match selection {
-        Ok(idx) => {
-            let task_id = tasks[idx].id;
-            show_task_details(task_id);
-        }
-        Err(_) => {}
+    if let Ok(idx) = selection {
+        let task_id = tasks[idx].id;
+        show_task_details(task_id);
     }
 }
 
-pub fn show_today_tasks() {
-    let tasks = load_tasks();
+/// Shows the open tasks due today, narrowed down by `query`.
+pub fn show_today_tasks(query: Query) {
     let today = Local::now().format("%Y-%m-%d").to_string();
+    let task_filter = TaskFilter {
+        closed: Some(false),
+        due_on: Some(today.clone()),
+        ..TaskFilter::default()
+    };
     
-    let today_tasks: Vec<Task> = tasks
-        .into_iter()
-        .filter(|task| {
-            task.status != "done" && 
-            task.due_date.as_ref().map_or(false, |due| due == &today)
-        })
-        .collect();
+    let mut today_tasks = list_or_exit(&task_filter);
+    if !query.is_empty() {
+        let data = load_or_exit();
+        today_tasks.retain(|t| query.matches(t, &data));
+    }
     
     if today_tasks.is_empty() {
         println!("{}", "No tasks due today!".green());
@@ -635,102 +1140,77 @@ pub fn show_today_tasks() {
     }
     
     println!("{} {}", "Tasks due today:".cyan().bold(), today.bright_white());
-    let colored_tasks: Vec<Task> = today_tasks
-        .iter()
-        .map(|task| {
-            let mut colored_task = task.clone();
-            colored_task.priority = format_priority(&task.priority);
-            colored_task.status = format_status(&task.status);
-            colored_task
-        })
-        .collect();
     println!();
-    for task in &colored_tasks {
-        let id_str = format!("[{}]", task.id).cyan().bold();
-        let priority_str = format!("[{}]", task.priority);
-        let status_str = format!("[{}]", task.status);
-        let progress_str = format_progress_bar(task.progress);
-        
-        let due_str = if let Some(due) = &task.due_date {
-            format!("(Due: {})", due).yellow()
-        } else {
-            "".normal()
-        };
-        
-        let tags_str = if !task.tags.is_empty() {
-            format!("#{}",  task.tags.join(" #")).bright_blue()
-        } else {
-            "".normal()
-        };
-        
-        let subtasks_str = if !task.subtasks.is_empty() {
-            let completed = task.subtasks.iter().filter(|s| s.completed).count();
-            format!("[{}/{}]", completed, task.subtasks.len()).bright_magenta()
-        } else {
-            "".normal()
-        };
-        
-        println!("{} {} {} {} {} {} {} {}", 
-            id_str,
-            priority_str,
-            status_str,
-            progress_str,
-            task.name.bright_white(),
-            due_str,
-            tags_str,
-            subtasks_str
-        );
+    let scale = PriorityScale::load();
+    let blocked = crate::deps::blocked_tasks(&today_tasks);
+    for task in &today_tasks {
+        print_task_line(task, &scale, blocked.get(&task.id).map_or(&[], Vec::as_slice));
     }
     println!();
 }
 
+/// Writes the tasks matching `query`, closed ones included, to stdout as
+/// a JSON array.
+pub fn export_tasks(query: Query) {
+    let data = load_or_exit();
+    let tasks = query.select(&data, true);
+    match serde_json::to_string_pretty(&tasks) {
+        Ok(json) => println!("{}", json),
+        Err(e) => println!("{} {}", "Error exporting tasks:".red().bold(), e),
+    }
+}
+
+/// Sets task `id` to `progress` percent inside a transaction; 100%
+/// completes it through [`complete_in`]. Returns `None` for a closed task.
+fn set_progress_in(
+    data: &mut TaskData,
+    id: usize,
+    progress: u8,
+) -> Result<Option<(String, Option<Completion>)>, StoreError> {
+    let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    if task.status.is_closed() {
+        return Ok(None);
+    }
+    
+    let name = task.name.clone();
+    if progress == 100 {
+        return Ok(Some((name, complete_in(data, id)?)));
+    }
+    task.progress = progress;
+    if progress > 0 && task.status == Status::Pending {
+        task.status = Status::InProgress;
+    }
+    Ok(Some((name, None)))
+}
+
 pub fn update_task_progress(id: usize, progress: u8) {
-    let mut tasks = load_tasks();
+    let store = store_or_exit();
+    let progress = progress.min(100);
     
-    let task_idx = tasks.iter().position(|t| t.id == id);
+    let result = store.modify(|data| set_progress_in(data, id, progress));
     
-    match task_idx {
-        Some(idx) => {
-            if tasks[idx].status == "done" {
-                println!("{}", "Cannot update progress of completed task".yellow());
-                return;
-            }
-            
-            let progress = progress.min(100);
-            tasks[idx].progress = progress;
-            
-            if progress == 100 {
-                tasks[idx].status = "done".to_string();
-                tasks[idx].completed_at = Some(Local::now().format("%Y-%m-%d %H:%M").to_string());
-                
-                for subtask in &mut tasks[idx].subtasks {
-                    subtask.completed = true;
-                }
-            }
-            
-            match save_tasks(&tasks) {
-                Ok(_) => {
-                    println!(
-                        "{} {} {}",
-                        "✓ Updated progress for task:".green().bold(),
-                        tasks[idx].name.bright_white(),
-                        format!("({}%)", progress).cyan()
-                    );
-                }
-                Err(e) => {
-                    println!("{} {}", "Error updating task progress:".red().bold(), e);
-                }
+    match result {
+        Ok(Some((name, completion))) => {
+            println!(
+                "{} {} {}",
+                "✓ Updated progress for task:".green().bold(),
+                name.bright_white(),
+                format!("({}%)", progress).cyan()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
             }
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+        Ok(None) => {
+            println!("{}", "Cannot update progress of a closed task".yellow());
         }
+        Err(e) => print_store_error("Error updating task progress:", &e),
     }
 }
 
 pub fn interactive_update_progress() {
-    let tasks = load_tasks();
-    let pending_tasks: Vec<&Task> = tasks.iter().filter(|t| t.status == "pending").collect();
+    let tasks = load_or_exit().tasks;
+    let pending_tasks: Vec<&Task> = tasks.iter().filter(|t| !t.status.is_closed()).collect();
     
     if pending_tasks.is_empty() {
         println!("{}", "No pending tasks to update".yellow());
@@ -747,20 +1227,17 @@ pub fn interactive_update_progress() {
         .items(&task_names)
         .interact();
     
-    match selection {
-        Ok(idx) => {
-            let task_id = pending_tasks[idx].id;
-            let current_progress = pending_tasks[idx].progress;
-            
-            let progress: u8 = Input::new()
-                .with_prompt("Enter progress percentage (0-100)")
-                .with_initial_text(&current_progress.to_string())
-                .interact_text()
-                .unwrap_or(current_progress);
-            
-            update_task_progress(task_id, progress);
-        }
-        Err(_) => {}
+    if let Ok(idx) = selection {
+        let task_id = pending_tasks[idx].id;
+        let current_progress = pending_tasks[idx].progress;
+        
+        let progress: u8 = Input::new()
+            .with_prompt("Enter progress percentage (0-100)")
+            .with_initial_text(current_progress.to_string())
+            .interact_text()
+            .unwrap_or(current_progress);
+        
+        update_task_progress(task_id, progress);
     }
 }
 
@@ -778,4 +1255,63 @@ pub fn print_welcome_banner() {
     println!("{}", "----------------------------------------".bright_blue());
     println!("Created by Miisan");
     println!();
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+    use crate::model::SubTask;
+
+    /// A store holding one occurrence, task 1, of a daily series.
+    fn recurring(subtasks: Vec<SubTask>) -> TaskData {
+        let mut first = task(1, "Water the plants");
+        first.due_date = Some("2100-01-01".to_string());
+        first.subtasks = subtasks;
+        let series = Series::from_task(&first, RecurrenceRule::Daily);
+        first.series = Some(series.uuid.clone());
+        let mut data = TaskData::from_legacy(vec![first]);
+        data.series.push(series);
+        data
+    }
+
+    fn next_occurrence(data: &TaskData) -> &Task {
+        let next = data.find(2).expect("the series moved on to task 2");
+        assert_eq!(next.series, data.find(1).unwrap().series);
+        assert_eq!(next.status, Status::Pending);
+        next
+    }
+
+    #[test]
+    fn full_progress_moves_a_series_on() {
+        let mut data = recurring(Vec::new());
+        let (_, completion) = set_progress_in(&mut data, 1, 100).unwrap().unwrap();
+
+        assert_eq!(completion.and_then(|c| c.next).map(|t| t.id), Some(2));
+        assert_eq!(data.find(1).unwrap().status, Status::Done);
+        assert_eq!(next_occurrence(&data).due_date.as_deref(), Some("2100-01-02"));
+    }
+
+    #[test]
+    fn checking_the_last_subtask_moves_a_series_on() {
+        let mut data = recurring(vec![SubTask::new("Front"), SubTask::new("Back")]);
+        data.find_mut(1).unwrap().assign_subtask_ids();
+
+        let task = data.find_mut(1).unwrap();
+        task.subtasks[0].set_completed(true);
+        assert!(settle_subtasks_in(&mut data, 1, ReopenState::default()).unwrap().is_none());
+        assert_eq!(data.find(1).unwrap().status, Status::InProgress);
+
+        let task = data.find_mut(1).unwrap();
+        let before = task.reopen_state();
+        task.subtasks[1].set_completed(true);
+        let completion = settle_subtasks_in(&mut data, 1, before).unwrap();
+
+        assert!(completion.is_some_and(|c| c.next.is_some()));
+        assert!(next_occurrence(&data).subtasks.iter().all(|s| !s.completed));
+
+        // Reopening goes back to before the last subtask was checked.
+        let done = data.find_mut(1).unwrap();
+        assert!(done.reopen());
+        assert_eq!(done.subtasks.iter().map(|s| s.completed).collect::<Vec<_>>(), vec![true, false]);
+    }
+}
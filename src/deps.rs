@@ -0,0 +1,204 @@
+use colored::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit};
+use crate::model::{Task, TaskData};
+use crate::store::StoreError;
+
+/// Prerequisites of `task` that are still open. Done and cancelled tasks
+/// no longer hold anything up.
+pub fn open_prerequisites(data: &TaskData, task: &Task) -> Vec<usize> {
+    task.depends_on
+        .iter()
+        .copied()
+        .filter(|dep| data.find(*dep).is_some_and(|t| !t.status.is_closed()))
+        .collect()
+}
+
+/// Open prerequisites for each task in `tasks` that is waiting on any.
+/// Prerequisites may be outside `tasks`, so the whole store is read, but
+/// only when one of the tasks has dependencies at all.
+pub fn blocked_tasks(tasks: &[Task]) -> HashMap<usize, Vec<usize>> {
+    if tasks.iter().all(|t| t.depends_on.is_empty()) {
+        return HashMap::new();
+    }
+
+    let data = load_or_exit();
+    tasks
+        .iter()
+        .filter(|t| !t.status.is_closed())
+        .map(|t| (t.id, open_prerequisites(&data, t)))
+        .filter(|(_, blockers)| !blockers.is_empty())
+        .collect()
+}
+
+/// IDs of the tasks that list `id` as a prerequisite.
+pub fn dependents(data: &TaskData, id: usize) -> Vec<usize> {
+    data.tasks
+        .iter()
+        .filter(|t| t.depends_on.contains(&id))
+        .map(|t| t.id)
+        .collect()
+}
+
+/// The dependency chain from `from` to `to`, if `from` already depends on
+/// `to` directly or through other tasks.
+fn dependency_path(data: &TaskData, from: usize, to: usize) -> Option<Vec<usize>> {
+    let mut stack = vec![vec![from]];
+    let mut seen = HashSet::new();
+
+    while let Some(path) = stack.pop() {
+        let current = *path.last()?;
+        if current == to {
+            return Some(path);
+        }
+        if !seen.insert(current) {
+            continue;
+        }
+        if let Some(task) = data.find(current) {
+            for dep in &task.depends_on {
+                let mut next = path.clone();
+                next.push(*dep);
+                stack.push(next);
+            }
+        }
+    }
+    None
+}
+
+pub fn format_ids(ids: &[usize]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Makes task `id` wait for each of `prerequisites`. Rejects the whole
+/// change if any of them would close a cycle.
+pub fn add_dependencies(id: usize, prerequisites: Vec<usize>) {
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        data.find(id).ok_or(StoreError::NotFound(id))?;
+
+        let mut added = Vec::new();
+        for &dep in &prerequisites {
+            data.find(dep).ok_or(StoreError::NotFound(dep))?;
+            if dep == id {
+                return Err(StoreError::Rejected(format!("Task {} can't depend on itself", id)));
+            }
+            if let Some(path) = dependency_path(data, dep, id) {
+                let cycle: Vec<String> = std::iter::once(id)
+                    .chain(path)
+                    .map(|id| id.to_string())
+                    .collect();
+                return Err(StoreError::Rejected(format!(
+                    "Task {} already depends on task {}; this would create a cycle ({})",
+                    dep,
+                    id,
+                    cycle.join(" → ")
+                )));
+            }
+
+            let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+            if !task.depends_on.contains(&dep) {
+                task.depends_on.push(dep);
+                added.push(dep);
+            }
+        }
+
+        let task = data.find(id).ok_or(StoreError::NotFound(id))?;
+        Ok((task.name.clone(), added))
+    });
+
+    match result {
+        Ok((_, added)) if added.is_empty() => {
+            println!("{}", "Dependencies already exist; nothing changed".yellow());
+        }
+        Ok((name, added)) => {
+            println!(
+                "{} {} {}",
+                "✓ Task".green().bold(),
+                name.bright_white(),
+                format!("now depends on {}", format_ids(&added)).green()
+            );
+        }
+        Err(e) => print_store_error("Error adding dependency:", &e),
+    }
+}
+
+pub fn remove_dependencies(id: usize, prerequisites: Vec<usize>) {
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        let removed: Vec<usize> = prerequisites
+            .iter()
+            .copied()
+            .filter(|dep| task.depends_on.contains(dep))
+            .collect();
+        task.depends_on.retain(|dep| !prerequisites.contains(dep));
+        Ok((task.name.clone(), removed))
+    });
+
+    match result {
+        Ok((_, removed)) if removed.is_empty() => {
+            println!("{}", "No such dependencies; nothing changed".yellow());
+        }
+        Ok((name, removed)) => {
+            println!(
+                "{} {} {}",
+                "✓ Task".green().bold(),
+                name.bright_white(),
+                format!("no longer depends on {}", format_ids(&removed)).green()
+            );
+        }
+        Err(e) => print_store_error("Error removing dependency:", &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+    use crate::model::Status;
+
+    /// Tasks 1..=n, where `edges` lists (task, prerequisite) pairs.
+    fn graph(n: usize, edges: &[(usize, usize)]) -> TaskData {
+        let mut tasks: Vec<Task> = (1..=n).map(|id| task(id, &format!("task {}", id))).collect();
+        for &(id, dep) in edges {
+            tasks[id - 1].depends_on.push(dep);
+        }
+        TaskData::from_legacy(tasks)
+    }
+
+    #[test]
+    fn finds_an_indirect_dependency_chain() {
+        let data = graph(3, &[(1, 2), (2, 3)]);
+        assert_eq!(dependency_path(&data, 1, 3), Some(vec![1, 2, 3]));
+        assert_eq!(dependency_path(&data, 3, 1), None);
+    }
+
+    #[test]
+    fn a_new_edge_closing_a_loop_is_detected() {
+        // 1 → 2 → 3 already; making 3 wait on 1 would close the loop.
+        let data = graph(3, &[(1, 2), (2, 3)]);
+        assert!(dependency_path(&data, 1, 3).is_some());
+        // A diamond shares a prerequisite without any cycle.
+        let diamond = graph(4, &[(1, 2), (1, 3), (2, 4), (3, 4)]);
+        assert_eq!(dependency_path(&diamond, 4, 1), None);
+        assert!(dependency_path(&diamond, 1, 4).is_some());
+    }
+
+    #[test]
+    fn stops_on_existing_cycles_in_hand_edited_data() {
+        let data = graph(3, &[(1, 2), (2, 1)]);
+        assert_eq!(dependency_path(&data, 1, 3), None);
+    }
+
+    #[test]
+    fn only_open_prerequisites_block() {
+        let mut data = graph(3, &[(1, 2), (1, 3)]);
+        data.find_mut(2).unwrap().status = Status::Done;
+        let waiting = data.find(1).unwrap();
+        assert_eq!(open_prerequisites(&data, waiting), vec![3]);
+        assert_eq!(dependents(&data, 3), vec![1]);
+    }
+}
@@ -0,0 +1,203 @@
+use colored::*;
+use dialoguer::Select;
+use serde_json::Value;
+use std::fs;
+
+use crate::model::{Task, TaskData, get_tasks_file};
+use crate::store::JsonStore;
+use crate::store::json::{parse_task_data, quarantine};
+use crate::store::schema::{detect_version, CURRENT_SCHEMA_VERSION};
+
+pub fn run_doctor() {
+    let path = get_tasks_file();
+
+    if !path.exists() {
+        println!("{} {}", "No task store yet at".yellow(), path.display());
+        return;
+    }
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("{} {}", "Error reading task store:".red().bold(), e);
+            return;
+        }
+    };
+
+    // A newer rtask may use a layout this build can't parse; that isn't
+    // damage, so it is never offered for repair.
+    if let Ok(version) = detect_version(&contents) {
+        if version > CURRENT_SCHEMA_VERSION {
+            println!(
+                "{} {} {}",
+                "Task store was written by a newer rtask:".yellow().bold(),
+                path.display(),
+                format!("(schema version {})", version).cyan()
+            );
+            println!(
+                "{}",
+                format!(
+                    "This build supports schema version {} and won't repair or modify it; upgrade rtask",
+                    CURRENT_SCHEMA_VERSION
+                )
+                .yellow()
+            );
+            return;
+        }
+    }
+
+    let error = match parse_task_data(&contents) {
+        Ok((data, version)) => {
+            println!(
+                "{} {} {}",
+                "✓ Task store is healthy:".green().bold(),
+                path.display().to_string().bright_white(),
+                format!(
+                    "({} tasks, next ID {}, schema version {})",
+                    data.tasks.len(),
+                    data.next_id,
+                    version
+                )
+                .cyan()
+            );
+            if version < CURRENT_SCHEMA_VERSION {
+                println!(
+                    "{}",
+                    format!(
+                        "Will be upgraded to schema version {} on the next change",
+                        CURRENT_SCHEMA_VERSION
+                    )
+                    .yellow()
+                );
+            }
+            return;
+        }
+        Err(e) => e,
+    };
+
+    println!("{} {}", "✗ Task store is corrupt:".red().bold(), path.display());
+    println!(
+        "{} line {}, column {}: {}",
+        "Error at".yellow(),
+        error.line(),
+        error.column(),
+        error
+    );
+    print_error_context(&contents, error.line(), error.column());
+
+    match quarantine(&path, &contents) {
+        Ok(copy) => println!("{} {}", "Original saved to:".cyan(), copy.display()),
+        Err(e) => {
+            println!("{} {}", "Error saving a copy of the store:".red().bold(), e);
+            println!("{}", "Not attempting a repair without a copy of the original".yellow());
+            return;
+        }
+    }
+
+    let salvaged = salvage_tasks(&contents);
+
+    let mut options = Vec::new();
+    if let Some((data, dropped)) = &salvaged {
+        options.push(format!(
+            "Keep the {} readable tasks and drop {} broken ones",
+            data.tasks.len(),
+            dropped
+        ));
+    }
+    options.push("Start over with an empty task list".to_string());
+    options.push("Leave the file as it is (fix it by hand)".to_string());
+
+    let selection = Select::new()
+        .with_prompt("How should the store be repaired?")
+        .items(&options)
+        .default(options.len() - 1)
+        .interact();
+
+    let repaired = match (selection, salvaged) {
+        (Ok(0), Some((data, _))) => data,
+        (Ok(idx), _) if idx == options.len() - 2 => TaskData::default(),
+        _ => {
+            println!("{}", "Left the task store untouched".yellow());
+            return;
+        }
+    };
+
+    let store = JsonStore::new(&path);
+    let _lock = match store.lock() {
+        Ok(lock) => lock,
+        Err(e) => {
+            println!("{} {}", "Error locking task store:".red().bold(), e);
+            return;
+        }
+    };
+
+    match store.write(&repaired) {
+        Ok(_) => {
+            println!(
+                "{} {}",
+                "✓ Repaired task store:".green().bold(),
+                format!("{} tasks", repaired.tasks.len()).cyan()
+            );
+        }
+        Err(e) => {
+            println!("{} {}", "Error writing repaired store:".red().bold(), e);
+        }
+    }
+}
+
+fn print_error_context(contents: &str, line: usize, column: usize) {
+    if line == 0 {
+        return;
+    }
+
+    if let Some(text) = contents.lines().nth(line - 1) {
+        let gutter = format!("{} | ", line);
+        println!("{}{}", gutter.cyan(), text);
+        println!(
+            "{}{}",
+            " ".repeat(gutter.len() + column.saturating_sub(1)),
+            "^".red().bold()
+        );
+    }
+}
+
+/// Recovers whatever tasks still deserialize from a store that is valid
+/// JSON but no longer matches the expected shape, e.g. after a hand edit
+/// removed a field. Returns the recovered data and how many entries were
+/// dropped, or `None` when the file isn't valid JSON at all.
+fn salvage_tasks(contents: &str) -> Option<(TaskData, usize)> {
+    let value: Value = serde_json::from_str(contents).ok()?;
+
+    let (entries, next_id, series) = match value {
+        Value::Array(entries) => (entries, None, Vec::new()),
+        Value::Object(mut map) => {
+            let next_id = map.get("next_id").and_then(Value::as_u64).map(|n| n as usize);
+            let series = match map.remove("series") {
+                Some(Value::Array(series)) => series,
+                _ => Vec::new(),
+            };
+            match map.remove("tasks") {
+                Some(Value::Array(entries)) => (entries, next_id, series),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let total = entries.len();
+    let tasks: Vec<Task> = entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry).ok())
+        .collect();
+    let dropped = total - tasks.len();
+
+    let mut data = TaskData::from_legacy(tasks);
+    data.series = series
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value(entry).ok())
+        .collect();
+    if let Some(next_id) = next_id {
+        data.next_id = data.next_id.max(next_id);
+    }
+    Some((data, dropped))
+}
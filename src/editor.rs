@@ -0,0 +1,642 @@
+use colored::*;
+use dialoguer::Confirm;
+use std::env;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::commands::{
+    print_completion, print_store_error, settle_subtasks_in, store_or_exit, validate_due_date, Completion,
+};
+use crate::model::{new_uuid, parse_project, Priority, PriorityScale, SubTask, Task, TaskData};
+use crate::store::StoreError;
+
+const HEADER: &str = "<!--
+  Edit the task below, then save and quit. Leave the file empty to cancel.
+  Subtasks are a checklist: \"- [ ] todo\" or \"- [x] done\", in order.
+  Indent an item two spaces to nest it under the one above, and add
+  details at the end like \"{priority: high, due: 2025-05-01}\".
+  Everything under \"## Notes\" is kept as the task description.
+-->";
+
+/// The parts of a task that can be edited as a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskDocument {
+    pub name: String,
+    pub priority: Priority,
+    pub due_date: Option<String>,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub subtasks: Vec<SubTask>,
+    pub description: Option<String>,
+}
+
+/// A problem with one line of an edited document. Line numbers are
+/// 1-based, as an editor shows them.
+#[derive(Debug, Clone)]
+pub struct DocumentError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+enum Section {
+    Fields,
+    Subtasks,
+    Notes,
+}
+
+impl TaskDocument {
+    pub fn from_task(task: &Task) -> Self {
+        Self {
+            name: task.name.clone(),
+            priority: task.priority.clone(),
+            due_date: task.due_date.clone(),
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            subtasks: task.subtasks.clone(),
+            description: task.description.clone(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n\n", HEADER);
+        out.push_str(&format!("name: {}\n", self.name));
+        out.push_str(&format!("priority: {}\n", self.priority));
+        out.push_str(&format!("due: {}\n", self.due_date.as_deref().unwrap_or("")));
+        out.push_str(&format!("tags: {}\n", self.tags.join(", ")));
+        out.push_str(&format!("project: {}\n", self.project.as_deref().unwrap_or("")));
+
+        out.push_str("\n## Subtasks\n\n");
+        render_subtasks(&mut out, &self.subtasks, 0);
+
+        out.push_str("\n## Notes\n\n");
+        if let Some(description) = &self.description {
+            out.push_str(description);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Parses an edited document. `current` is the priority the task had
+    /// before editing; it stays valid even if it is no longer on the scale.
+    pub fn parse(
+        text: &str,
+        scale: &PriorityScale,
+        current: &Priority,
+    ) -> Result<Self, Vec<DocumentError>> {
+        let mut errors = Vec::new();
+        let mut error = |line: usize, message: String| errors.push(DocumentError { line, message });
+
+        let mut name = None;
+        let mut priority = None;
+        let mut due_date = None;
+        let mut tags = Vec::new();
+        let mut project = None;
+        let mut subtasks = Vec::new();
+        let mut notes: Vec<&str> = Vec::new();
+
+        let mut section = Section::Fields;
+        let mut in_comment = false;
+        // Indentation and position of each open level of the subtask tree.
+        let mut levels: Vec<(usize, usize)> = Vec::new();
+
+        for (idx, raw) in text.lines().enumerate() {
+            let line_no = idx + 1;
+
+            if let Section::Notes = section {
+                notes.push(raw);
+                continue;
+            }
+
+            let line = raw.trim();
+            if in_comment || line.starts_with("<!--") {
+                in_comment = !line.ends_with("-->");
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(heading) = line.strip_prefix("## ") {
+                match heading.trim().to_lowercase().as_str() {
+                    "subtasks" => section = Section::Subtasks,
+                    "notes" => section = Section::Notes,
+                    other => error(line_no, format!("unknown section `{}`", other)),
+                }
+                continue;
+            }
+
+            match section {
+                Section::Fields => {
+                    let Some((key, value)) = line.split_once(':') else {
+                        error(line_no, format!("expected `field: value`, found `{}`", line));
+                        continue;
+                    };
+                    let value = value.trim();
+                    match key.trim().to_lowercase().as_str() {
+                        "name" if value.is_empty() => {
+                            error(line_no, "task name can't be empty".to_string())
+                        }
+                        "name" => name = Some(value.to_string()),
+                        "priority" if value.eq_ignore_ascii_case(current.as_str()) => {
+                            priority = Some(current.clone())
+                        }
+                        "priority" => match scale.parse(value) {
+                            Ok(p) => priority = Some(p),
+                            Err(e) => error(line_no, e),
+                        },
+                        "due" if value.is_empty() => due_date = None,
+                        "due" => match validate_due_date(value) {
+                            Ok(date) => due_date = Some(date),
+                            Err(e) => error(line_no, e),
+                        },
+                        "tags" => {
+                            tags = value
+                                .split(',')
+                                .map(|t| t.trim().to_string())
+                                .filter(|t| !t.is_empty())
+                                .collect();
+                        }
+                        "project" if value.is_empty() => project = None,
+                        "project" => match parse_project(value) {
+                            Ok(p) => project = Some(p),
+                            Err(e) => error(line_no, e),
+                        },
+                        other => error(line_no, format!("unknown field `{}`", other)),
+                    }
+                }
+                Section::Subtasks => {
+                    let parsed = line.strip_prefix("- [").and_then(|rest| {
+                        let (mark, name) = rest.split_once(']')?;
+                        let completed = match mark {
+                            " " | "" => false,
+                            "x" | "X" => true,
+                            _ => return None,
+                        };
+                        Some((completed, name.trim()))
+                    });
+                    let Some((completed, text)) = parsed else {
+                        error(
+                            line_no,
+                            format!("expected `- [ ] subtask` or `- [x] subtask`, found `{}`", line),
+                        );
+                        continue;
+                    };
+
+                    let (name, details) = split_details(text);
+                    if name.is_empty() {
+                        error(line_no, "subtask name can't be empty".to_string());
+                        continue;
+                    }
+
+                    let mut subtask = SubTask {
+                        name: name.to_string(),
+                        completed,
+                        ..SubTask::default()
+                    };
+                    for detail in details.into_iter().flat_map(|d| d.split(',')) {
+                        let Some((key, value)) = detail.split_once(':') else {
+                            error(line_no, format!("expected `key: value` in details, found `{}`", detail.trim()));
+                            continue;
+                        };
+                        let value = value.trim();
+                        match key.trim().to_lowercase().as_str() {
+                            "priority" => match scale.parse(value) {
+                                Ok(p) => subtask.priority = Some(p),
+                                Err(e) => error(line_no, e),
+                            },
+                            "due" => match validate_due_date(value) {
+                                Ok(date) => subtask.due_date = Some(date),
+                                Err(e) => error(line_no, e),
+                            },
+                            other => error(line_no, format!("unknown subtask detail `{}`", other)),
+                        }
+                    }
+
+                    let indent = raw.len() - raw.trim_start().len();
+                    while levels.last().is_some_and(|(level, _)| *level >= indent) {
+                        levels.pop();
+                    }
+                    let parent: Vec<usize> = levels.iter().map(|(_, idx)| *idx).collect();
+                    let siblings = children_mut(&mut subtasks, &parent);
+                    siblings.push(subtask);
+                    levels.push((indent, siblings.len() - 1));
+                }
+                Section::Notes => unreachable!(),
+            }
+        }
+
+        let description = notes.join("\n").trim().to_string();
+
+        if name.is_none() && errors.is_empty() {
+            errors.push(DocumentError {
+                line: 1,
+                message: "missing `name:` field".to_string(),
+            });
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Self {
+            name: name.unwrap_or_default(),
+            priority: priority.unwrap_or_else(|| current.clone()),
+            due_date,
+            tags,
+            project,
+            subtasks,
+            description: if description.is_empty() { None } else { Some(description) },
+        })
+    }
+
+    /// Writes the document back into `task` and returns the names of the
+    /// fields that changed. Changed subtasks still need rolling up into
+    /// the task with [`settle_subtasks_in`].
+    pub fn apply(&self, task: &mut Task) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+
+        if self.name != task.name {
+            task.name = self.name.clone();
+            changed.push("name");
+        }
+        if self.priority != task.priority {
+            task.priority = self.priority.clone();
+            changed.push("priority");
+        }
+        if self.due_date != task.due_date {
+            task.due_date = self.due_date.clone();
+            changed.push("due date");
+        }
+        if self.tags != task.tags {
+            task.tags = self.tags.clone();
+            changed.push("tags");
+        }
+        if self.project != task.project {
+            task.project = self.project.clone();
+            changed.push("project");
+        }
+        let subtasks = self.subtasks_keeping_ids(task);
+        if subtasks != task.subtasks {
+            task.subtasks = subtasks;
+            task.assign_subtask_ids();
+            changed.push("subtasks");
+        }
+        if self.description != task.description {
+            task.description = self.description.clone();
+            changed.push("description");
+        }
+        changed
+    }
+}
+
+impl TaskDocument {
+    /// The edited subtask tree, with each subtask that still has its old
+    /// name keeping its old ID. New and renamed subtasks get ID 0 until
+    /// [`Task::assign_subtask_ids`] numbers them.
+    fn subtasks_keeping_ids(&self, task: &Task) -> Vec<SubTask> {
+        let mut old_ids: Vec<(String, usize)> = task
+            .all_subtasks()
+            .iter()
+            .map(|s| (s.name.clone(), s.id))
+            .collect();
+
+        fn visit(nodes: &mut [SubTask], old_ids: &mut Vec<(String, usize)>) {
+            for node in nodes {
+                if let Some(pos) = old_ids.iter().position(|(name, _)| *name == node.name) {
+                    node.id = old_ids.remove(pos).1;
+                }
+                visit(&mut node.subtasks, old_ids);
+            }
+        }
+
+        let mut subtasks = self.subtasks.clone();
+        visit(&mut subtasks, &mut old_ids);
+        subtasks
+    }
+}
+
+fn render_subtasks(out: &mut String, subtasks: &[SubTask], depth: usize) {
+    for subtask in subtasks {
+        let mark = if subtask.completed { 'x' } else { ' ' };
+        let mut details = Vec::new();
+        if let Some(priority) = &subtask.priority {
+            details.push(format!("priority: {}", priority));
+        }
+        if let Some(due) = &subtask.due_date {
+            details.push(format!("due: {}", due));
+        }
+        let details = if details.is_empty() {
+            String::new()
+        } else {
+            format!(" {{{}}}", details.join(", "))
+        };
+
+        out.push_str(&format!("{}- [{}] {}{}\n", "  ".repeat(depth), mark, subtask.name, details));
+        render_subtasks(out, &subtask.subtasks, depth + 1);
+    }
+}
+
+/// The children list at `path` in a tree being built top-down, where every
+/// position on the path is known to exist.
+fn children_mut<'a>(nodes: &'a mut Vec<SubTask>, path: &[usize]) -> &'a mut Vec<SubTask> {
+    match path.split_first() {
+        Some((first, rest)) => children_mut(&mut nodes[*first].subtasks, rest),
+        None => nodes,
+    }
+}
+
+/// The user's editor: `$VISUAL`, then `$EDITOR`, then a platform default.
+fn editor_command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+fn launch_editor(path: &Path) -> Result<(), String> {
+    let editor = editor_command();
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Couldn't start editor `{}`: {}", editor, e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Editor `{}` exited with {}", editor, status))
+    }
+}
+
+/// Splits a subtask line into its name and a trailing `{key: value, ...}`
+/// details block. Braces that hold anything else are part of the name, so
+/// a subtask called "Fix {config}" stays as it is.
+fn split_details(text: &str) -> (&str, Option<&str>) {
+    let Some((name, details)) = text.strip_suffix('}').and_then(|t| t.rsplit_once(" {")) else {
+        return (text, None);
+    };
+    let is_pair = |part: &str| {
+        part.split_once(':').is_some_and(|(key, value)| {
+            let key = key.trim();
+            !key.is_empty() && key.chars().all(char::is_alphanumeric) && !value.trim().is_empty()
+        })
+    };
+    if details.split(',').all(is_pair) {
+        (name.trim(), Some(details))
+    } else {
+        (text, None)
+    }
+}
+
+/// Creates a new directory only the user can open, for the file being
+/// edited. Creating it fails rather than reuse anything already at the
+/// path, so no one else can plant a file or symlink where the task goes.
+fn private_temp_dir() -> io::Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("rtask-{}", new_uuid()));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Opens the task in the user's editor until the document parses or the
+/// user gives up. Returns `None` when the edit was cancelled.
+fn edit_document(task: &Task) -> Result<Option<TaskDocument>, String> {
+    let scale = PriorityScale::load();
+    let dir = private_temp_dir().map_err(|e| format!("Couldn't create a temporary file: {}", e))?;
+    let path = dir.join(format!("task-{}.md", task.id));
+    let mut text = TaskDocument::from_task(task).render();
+
+    let result = loop {
+        let written = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        if let Err(e) = written {
+            break Err(format!("Couldn't write {}: {}", path.display(), e));
+        }
+        if let Err(e) = launch_editor(&path) {
+            break Err(e);
+        }
+        text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => break Err(format!("Couldn't read {}: {}", path.display(), e)),
+        };
+
+        if text.trim().is_empty() {
+            break Ok(None);
+        }
+
+        match TaskDocument::parse(&text, &scale, &task.priority) {
+            Ok(document) => break Ok(Some(document)),
+            Err(errors) => {
+                println!("{}", "The edited task has errors:".red().bold());
+                for error in &errors {
+                    println!("  {}", error);
+                }
+                let retry = Confirm::new()
+                    .with_prompt("Re-open the editor?")
+                    .default(true)
+                    .interact()
+                    .unwrap_or(false);
+                if !retry {
+                    break Ok(None);
+                }
+            }
+        }
+    };
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Writes an edited document back into the task it was made from, inside
+/// a transaction. Refuses if the stored task no longer matches `original`,
+/// the copy the editor was opened on, so a change made meanwhile isn't
+/// overwritten. Returns the task's name, the changed fields and the
+/// completion if the edit checked off its last subtask.
+fn apply_edit(
+    data: &mut TaskData,
+    original: &Task,
+    document: &TaskDocument,
+) -> Result<(String, Vec<&'static str>, Option<Completion>), StoreError> {
+    let id = original.id;
+    let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    if serde_json::to_value(&*task)? != serde_json::to_value(original)? {
+        return Err(StoreError::Rejected(format!(
+            "Task {} changed while it was open in the editor; nothing was saved",
+            id
+        )));
+    }
+
+    let before = task.reopen_state();
+    let name = task.name.clone();
+    let changed = document.apply(task);
+    let completion = if changed.contains(&"subtasks") {
+        settle_subtasks_in(data, id, before)?
+    } else {
+        None
+    };
+    Ok((name, changed, completion))
+}
+
+pub fn edit_task_in_editor(id: usize) {
+    let store = store_or_exit();
+
+    let task = match store.get(id) {
+        Ok(Some(task)) => task,
+        Ok(None) => {
+            println!("{}", format!("Task with ID {} not found", id).red());
+            return;
+        }
+        Err(e) => {
+            print_store_error("Error loading tasks:", &e);
+            return;
+        }
+    };
+
+    let document = match edit_document(&task) {
+        Ok(Some(document)) => document,
+        Ok(None) => {
+            println!("{}", "Edit cancelled; nothing changed".yellow());
+            return;
+        }
+        Err(e) => {
+            println!("{}", e.red().bold());
+            return;
+        }
+    };
+
+    let result = store.modify(|data| apply_edit(data, &task, &document));
+    if let Err(StoreError::Rejected(_)) = &result {
+        println!("{}", "Your edited version, not saved:".yellow().bold());
+        println!("{}", document.render());
+    }
+
+    match result {
+        Ok((_, changed, _)) if changed.is_empty() => {
+            println!("{}", "Task already matches; nothing changed".yellow());
+        }
+        Ok((name, changed, completion)) => {
+            println!(
+                "{} {} {}",
+                "✓ Modified task:".green().bold(),
+                name.bright_white(),
+                format!("({})", changed.join(", ")).cyan()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
+            }
+        }
+        Err(e) => print_store_error("Error modifying task:", &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::default_priority_levels;
+
+    fn scale() -> PriorityScale {
+        PriorityScale::new(default_priority_levels())
+    }
+
+    fn medium() -> Priority {
+        Priority::from("medium".to_string())
+    }
+
+    fn subtask(name: &str, completed: bool, subtasks: Vec<SubTask>) -> SubTask {
+        SubTask {
+            completed,
+            subtasks,
+            ..SubTask::new(name)
+        }
+    }
+
+    #[test]
+    fn a_rendered_document_parses_back() {
+        let mut release = subtask("Tag the release", false, Vec::new());
+        release.priority = Some(Priority::from("high".to_string()));
+        release.due_date = Some("2100-05-01".to_string());
+        let document = TaskDocument {
+            name: "Ship 2.0".to_string(),
+            priority: Priority::from("low".to_string()),
+            due_date: Some("2100-06-01".to_string()),
+            tags: vec!["release".to_string(), "work".to_string()],
+            project: Some("acme.web".to_string()),
+            subtasks: vec![
+                subtask("Build", true, vec![subtask("Fix {config}", false, Vec::new())]),
+                release,
+            ],
+            description: Some("Notes\n\n- [ ] not a subtask".to_string()),
+        };
+
+        let text = document.render();
+        assert!(text.contains("  - [ ] Fix {config}\n"));
+        assert!(text.contains("- [ ] Tag the release {priority: high, due: 2100-05-01}\n"));
+        assert_eq!(TaskDocument::parse(&text, &scale(), &medium()).unwrap(), document);
+    }
+
+    #[test]
+    fn empty_fields_clear_and_a_missing_name_fails() {
+        let parsed = TaskDocument::parse("name: a\ndue:\nproject:\ntags: ,\n", &scale(), &medium()).unwrap();
+        assert_eq!((parsed.due_date, parsed.project), (None, None));
+        assert!(parsed.tags.is_empty());
+        assert_eq!(parsed.priority, medium());
+
+        let errors = TaskDocument::parse("priority: high\n", &scale(), &medium()).unwrap_err();
+        assert_eq!(errors[0].to_string(), "line 1: missing `name:` field");
+    }
+
+    #[test]
+    fn reports_each_bad_line() {
+        let text = "name: a\ncolour: red\n## Subtasks\n- [?] b\n- [ ] c {priority: urgent}\n";
+        let errors = TaskDocument::parse(text, &scale(), &medium()).unwrap_err();
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn refuses_to_overwrite_a_task_changed_while_editing() {
+        let original = crate::model::tests::task(1, "Draft");
+        let mut document = TaskDocument::from_task(&original);
+        document.name = "Final".to_string();
+
+        let mut data = TaskData::from_legacy(vec![original.clone()]);
+        data.find_mut(1).unwrap().tags.push("urgent".to_string());
+        assert!(matches!(apply_edit(&mut data, &original, &document), Err(StoreError::Rejected(_))));
+        assert_eq!(data.find(1).unwrap().name, "Draft");
+
+        let mut data = TaskData::from_legacy(vec![original.clone()]);
+        let (_, changed, _) = apply_edit(&mut data, &original, &document).unwrap();
+        assert_eq!(changed, vec!["name"]);
+        assert_eq!(data.find(1).unwrap().name, "Final");
+    }
+
+    #[test]
+    fn only_key_value_braces_are_details() {
+        assert_eq!(split_details("Plan {due: 2100-01-01}"), ("Plan", Some("due: 2100-01-01")));
+        assert_eq!(split_details("Fix {config}"), ("Fix {config}", None));
+        assert_eq!(split_details("Log {a: b, c}"), ("Log {a: b, c}", None));
+        assert_eq!(split_details("Map {key:}"), ("Map {key:}", None));
+        assert_eq!(split_details("{due: today}"), ("{due: today}", None));
+    }
+}
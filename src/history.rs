@@ -0,0 +1,277 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use colored::*;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::commands::{format_status, print_store_error};
+use crate::model::{
+    get_archive_dir, get_events_file, get_history_file, load_config, Status, StorageBackend, SubTask, TaskData,
+};
+use crate::store::events::{Event, EventRecord, EventStore};
+use crate::store::archive::Archive;
+
+fn parse_time(at: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S").ok()
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    if minutes < 1 {
+        return "<1m".to_string();
+    }
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// Renders a field value from an event in a line of text.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.is_empty() => "none".to_string(),
+        Value::Array(items) => items.iter().map(format_value).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// One line describing `event`. `data` holds the task as it was just
+/// before the event, for names that the event itself doesn't carry;
+/// `archived` says a removal moved the task to the archive.
+fn describe(event: &Event, data: &TaskData, archived: bool) -> Option<String> {
+    let line = match event {
+        Event::Created { task } => format!(
+            "{} \"{}\" ({}, {})",
+            "created".green(),
+            task.name,
+            task.priority,
+            task.status
+        ),
+        Event::StatusChanged { to: Status::Done, .. } => "completed".green().to_string(),
+        Event::StatusChanged { from, to, .. } => {
+            format!("status {} → {}", format_status(*from), format_status(*to))
+        }
+        Event::SubtaskToggled { id, subtask, completed } => {
+            let name = data
+                .find(*id)
+                .and_then(|t| t.all_subtasks().into_iter().find(|s| s.id == *subtask))
+                .map(|s| s.name.clone())
+                .unwrap_or_else(|| format!("#{}", subtask));
+            let action = if *completed { "checked" } else { "unchecked" };
+            format!("subtask \"{}\" {}", name, action)
+        }
+        Event::Changed { field, from, to, .. } => match field.as_str() {
+            "reopen" | "uuid" | "completed_at" => return None,
+            "subtasks" => describe_subtask_edit(from, to),
+            "description" => "description changed".to_string(),
+            "annotations" => match to.as_array().and_then(|notes| notes.last()) {
+                Some(note) => format!("annotated: {}", format_value(&note["text"])),
+                None => "annotations cleared".to_string(),
+            },
+            _ => format!(
+                "{} {} → {}",
+                field.replace('_', " "),
+                format_value(from),
+                format_value(to)
+            ),
+        },
+        Event::Removed { .. } if archived => "archived".yellow().to_string(),
+        Event::Removed { .. } => "removed".red().to_string(),
+        Event::SeriesChanged { .. } => return None,
+    };
+    Some(line)
+}
+
+/// Names the subtasks added or removed by an edit of the tree.
+fn describe_subtask_edit(from: &Value, to: &Value) -> String {
+    let names = |value: &Value| -> Vec<String> {
+        let nodes: Vec<SubTask> = serde_json::from_value(value.clone()).unwrap_or_default();
+        let mut names = Vec::new();
+        for node in &nodes {
+            names.extend(node.walk().into_iter().map(|s| s.name.clone()));
+        }
+        names
+    };
+    let (before, after) = (names(from), names(to));
+
+    let added: Vec<&String> = after.iter().filter(|n| !before.contains(n)).collect();
+    let removed: Vec<&String> = before.iter().filter(|n| !after.contains(n)).collect();
+    let list = |names: &[&String]| {
+        names.iter().map(|n| format!("\"{}\"", n)).collect::<Vec<_>>().join(", ")
+    };
+    match (added.is_empty(), removed.is_empty()) {
+        (false, true) => format!("subtask added: {}", list(&added)),
+        (true, false) => format!("subtask removed: {}", list(&removed)),
+        _ => "subtasks edited".to_string(),
+    }
+}
+
+/// Time spent in each status by one task, given its events, from its
+/// creation up to now, or until it was removed, completed or cancelled.
+fn time_in_states(records: &[&EventRecord]) -> Vec<(Status, Duration)> {
+    let mut totals: Vec<(Status, Duration)> = Vec::new();
+    let mut current: Option<(Status, NaiveDateTime)> = None;
+
+    let mut close = |current: Option<(Status, NaiveDateTime)>, until: NaiveDateTime| {
+        if let Some((status, since)) = current {
+            let spent = until - since;
+            match totals.iter_mut().find(|(s, _)| *s == status) {
+                Some((_, total)) => *total += spent,
+                None => totals.push((status, spent)),
+            }
+        }
+    };
+
+    for record in records {
+        let Some(at) = parse_time(&record.at) else {
+            continue;
+        };
+        match &record.event {
+            Event::Created { task } => current = Some((task.status, at)),
+            Event::StatusChanged { to, .. } => {
+                close(current, at);
+                current = Some((*to, at));
+            }
+            Event::Removed { .. } => {
+                close(current, at);
+                current = None;
+            }
+            _ => {}
+        }
+    }
+    // A task still done or cancelled stopped at that event; the time since
+    // isn't time spent on it.
+    if current.is_some_and(|(status, _)| !status.is_closed()) {
+        close(current, Local::now().naive_local());
+    }
+    totals
+}
+
+/// Reads the recorded history: the event log itself with that backend,
+/// or the `history.jsonl` kept next to any other. Changes made before
+/// history was kept aren't in it.
+fn read_events() -> Option<Vec<EventRecord>> {
+    let path = if load_config().backend == StorageBackend::Events {
+        get_events_file()
+    } else {
+        get_history_file()
+    };
+
+    match EventStore::new(path).events() {
+        Ok(events) => Some(events),
+        Err(e) => {
+            print_store_error("Error reading the task history:", &e);
+            None
+        }
+    }
+}
+
+/// Prints every recorded change to task `id`, oldest first, followed by
+/// how long it spent in each status.
+pub fn show_task_history(id: usize) {
+    let Some(events) = read_events() else {
+        return;
+    };
+
+    let records: Vec<&EventRecord> = events
+        .iter()
+        .filter(|r| r.event.task_id() == Some(id))
+        .collect();
+    if records.is_empty() {
+        println!("{}", format!("No history for task {}", id).yellow());
+        return;
+    }
+
+    println!("\n{}", format!("History of task {}", id).cyan().bold());
+    println!("{}", "-".repeat(18).cyan());
+
+    // Archiving takes a task out of the store like a removal does; its
+    // last removal was an archiving if the task is in the archive now.
+    let archived = matches!(Archive::new(get_archive_dir()).find(id), Ok(Some(_)));
+    let mut data = TaskData::default();
+    for (idx, record) in records.iter().enumerate() {
+        let last = idx + 1 == records.len();
+        if let Some(line) = describe(&record.event, &data, archived && last) {
+            println!("{}  {}", record.at.bright_black(), line);
+        }
+        let _ = record.event.apply(&mut data);
+    }
+
+    println!("\n{}", "Time in each state:".cyan().bold());
+    for (status, spent) in time_in_states(&records) {
+        println!("  {:<12} {}", status.as_str(), format_duration(spent));
+    }
+    println!();
+}
+
+/// Prints how long tasks spend in each status on average, across every
+/// task in the log.
+pub fn show_state_report() {
+    let Some(events) = read_events() else {
+        return;
+    };
+
+    let mut by_task: HashMap<usize, Vec<&EventRecord>> = HashMap::new();
+    for record in &events {
+        if let Some(id) = record.event.task_id() {
+            by_task.entry(id).or_default().push(record);
+        }
+    }
+    if by_task.is_empty() {
+        println!("{}", "No history yet".yellow());
+        return;
+    }
+
+    let mut totals: HashMap<Status, (usize, Duration)> = HashMap::new();
+    for records in by_task.values() {
+        for (status, spent) in time_in_states(records) {
+            let entry = totals.entry(status).or_insert((0, Duration::zero()));
+            entry.0 += 1;
+            entry.1 += spent;
+        }
+    }
+
+    println!("\n{}", "Time in each state".cyan().bold());
+    println!("{}", "-".repeat(18).cyan());
+    println!("  {:<12} {:>6}  {:>10}  {:>10}", "status", "tasks", "average", "total");
+    for status in Status::ALL {
+        if let Some((count, total)) = totals.get(&status) {
+            println!(
+                "  {:<12} {:>6}  {:>10}  {:>10}",
+                status.as_str(),
+                count,
+                format_duration(*total / *count as i32),
+                format_duration(*total)
+            );
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+
+    fn record(at: &str, event: Event) -> EventRecord {
+        EventRecord { seq: 0, at: format!("2024-01-01 {}", at), event }
+    }
+
+    #[test]
+    fn stops_counting_when_a_task_is_closed() {
+        let changed = |from, to| Event::StatusChanged { id: 1, from, to };
+        let records = [
+            record("09:00:00", Event::Created { task: Box::new(task(1, "a")) }),
+            record("10:00:00", changed(Status::Pending, Status::InProgress)),
+            record("12:30:00", changed(Status::InProgress, Status::Done)),
+        ];
+        let records: Vec<&EventRecord> = records.iter().collect();
+        assert_eq!(
+            time_in_states(&records),
+            vec![(Status::Pending, Duration::hours(1)), (Status::InProgress, Duration::minutes(150))]
+        );
+    }
+}
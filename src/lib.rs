@@ -1,3 +1,19 @@
 pub mod model;
 pub mod commands;
-pub mod subtasks;
\ No newline at end of file
+pub mod subtasks;
+pub mod store;
+pub mod doctor;
+pub mod migrate;
+pub mod modify;
+pub mod editor;
+pub mod notes;
+pub mod recur;
+pub mod deps;
+pub mod undo;
+pub mod history;
+pub mod backup;
+pub mod archive;
+pub mod lists;
+pub mod projects;
+pub mod tags;
+pub mod query;
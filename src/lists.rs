@@ -0,0 +1,179 @@
+use colored::*;
+use std::fs;
+
+use crate::model::{
+    active_list, find_local_store, get_list_dir, get_tasks_root, list_names, load_config,
+    local_store, save_config, DEFAULT_LIST, LOCAL_STORE_DIR,
+};
+use crate::store::{open_list, TaskFilter};
+
+pub fn list_exists(name: &str) -> bool {
+    name == DEFAULT_LIST || get_list_dir(name).is_dir()
+}
+
+/// Stops with an error if the active list doesn't exist, rather than
+/// quietly starting a new store under a mistyped name.
+pub fn require_active_list() {
+    let name = active_list();
+    if !list_exists(&name) {
+        println!(
+            "{}",
+            format!(
+                "No list named '{}'. Create it with `rtask list-create {}` or see `rtask lists`",
+                name, name
+            )
+            .red()
+        );
+        std::process::exit(1);
+    }
+}
+
+pub fn create_list(name: String) {
+    if list_exists(&name) {
+        println!("{}", format!("List '{}' already exists", name).yellow());
+        return;
+    }
+
+    match fs::create_dir_all(get_list_dir(&name)) {
+        Ok(()) => {
+            println!(
+                "{} {}",
+                "✓ Created list:".green().bold(),
+                name.bright_white()
+            );
+            let hint = if local_store().is_some() {
+                format!("Add to it with `rtask -L {} add ...`", name)
+            } else {
+                format!("Switch to it with `rtask use {}`, or add to it with `rtask -L {} add ...`", name, name)
+            };
+            println!("{}", hint.cyan());
+        }
+        Err(e) => {
+            println!("{} {}", "Error creating list:".red().bold(), e);
+        }
+    }
+}
+
+/// Makes `name` the list commands use from now on.
+pub fn use_list(name: String) {
+    if let Some(dir) = local_store() {
+        println!(
+            "{}",
+            format!(
+                "`rtask use` picks the list of the global store. In {} choose a list with `-L {}`, \
+                 or run `rtask --global use {}`",
+                dir.display(),
+                name,
+                name
+            )
+            .yellow()
+        );
+        return;
+    }
+    if !list_exists(&name) {
+        println!(
+            "{}",
+            format!("No list named '{}'. Create it with `rtask list-create {}`", name, name).red()
+        );
+        return;
+    }
+
+    let mut config = load_config();
+    if config.current_list == name {
+        println!("{}", format!("Already using list '{}'", name).yellow());
+        return;
+    }
+    config.current_list = name.clone();
+    match save_config(&config) {
+        Ok(_) => {
+            println!(
+                "{} {}",
+                "✓ Now using list:".green().bold(),
+                name.bright_white()
+            );
+        }
+        Err(e) => {
+            println!("{} {}", "Error saving configuration:".red().bold(), e);
+        }
+    }
+}
+
+/// Prints every list with how many of its tasks are open, marking the
+/// active one.
+pub fn show_lists() {
+    let active = active_list();
+    let open = TaskFilter {
+        closed: Some(false),
+        ..TaskFilter::default()
+    };
+
+    println!();
+    for name in list_names() {
+        let marker = if name == active { "*".green().bold() } else { " ".normal() };
+        let summary = match open_list(&name).and_then(|store| store.list(&open)) {
+            Ok(tasks) => format!("{} open", tasks.len()).normal(),
+            Err(e) => format!("unreadable: {}", e).red(),
+        };
+        let label = format!("{:<16}", name);
+        let label = if name == active { label.green().bold() } else { label.bright_white() };
+        println!("{} {} {}", marker, label, summary);
+    }
+    println!();
+}
+
+/// Where the lists in use are kept, marking a local store as such.
+pub fn store_label() -> String {
+    match local_store() {
+        Some(dir) => format!("{} (local)", dir.display()),
+        None => get_tasks_root().display().to_string(),
+    }
+}
+
+/// Which store and list commands are using, for the top of listings.
+pub fn active_store_label() -> String {
+    let store = store_label();
+    let list = active_list();
+    if list == DEFAULT_LIST {
+        store
+    } else {
+        format!("{}, list {}", store, list)
+    }
+}
+
+/// Creates a `.rtask/` store in the current directory. Commands run here
+/// or in any directory below it then use it instead of the global store.
+pub fn init_local_store() {
+    let dir = match std::env::current_dir() {
+        Ok(cwd) => cwd.join(LOCAL_STORE_DIR),
+        Err(e) => {
+            println!("{} {}", "Error reading the current directory:".red().bold(), e);
+            return;
+        }
+    };
+    if dir.is_dir() {
+        println!("{}", format!("{} already exists", dir.display()).yellow());
+        return;
+    }
+    let enclosing = find_local_store();
+
+    if let Err(e) = fs::create_dir_all(&dir) {
+        println!("{} {}", "Error creating the task store:".red().bold(), e);
+        return;
+    }
+    println!(
+        "{} {}",
+        "✓ Created a local task store in".green().bold(),
+        dir.display().to_string().bright_white()
+    );
+    if let Some(enclosing) = enclosing {
+        println!(
+            "{}",
+            format!("It takes the place of {} below this directory", enclosing.display()).cyan()
+        );
+    }
+    println!(
+        "{}",
+        "Commands run here and in subdirectories now use it; add --global to reach your other tasks"
+            .cyan()
+    );
+}
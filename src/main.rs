@@ -1,16 +1,33 @@
-mod model;
-mod commands;
-mod subtasks;
-
-use clap::{Parser, Subcommand};
-use model::is_config_exists;
-use commands::{
-    add_task, complete_task, interactive_mode, list_tasks, 
-    print_welcome_banner, remove_task, setup_config,
+use clap::{CommandFactory, Parser, Subcommand};
+use colored::Colorize;
+use rtask::model::is_config_exists;
+use rtask::commands::{
+    add_task, complete_tasks, interactive_mode, list_tasks, 
+    print_welcome_banner, remove_task, remove_tasks, reopen_task, export_tasks, ListOptions, set_task_status, setup_config, SortKey,
     show_task_details, show_today_tasks, update_task_progress
 };
-use subtasks::{
-    add_subtask, toggle_subtask, remove_subtask
+use rtask::subtasks::{
+    add_subtask, demote_task, parse_subtask_path, path_from_flags, promote_subtask, remove_subtask,
+    toggle_subtask, SubtaskPath,
+};
+use rtask::doctor::run_doctor;
+use rtask::migrate::migrate_store;
+use rtask::modify::{modify_tasks, TaskChanges};
+use rtask::editor::edit_task_in_editor;
+use rtask::notes::annotate_task;
+use rtask::recur::{list_series, set_series_rule, stop_series};
+use rtask::deps::{add_dependencies, remove_dependencies};
+use rtask::undo::{redo, undo};
+use rtask::backup::{list_backups, restore_backup};
+use rtask::archive::{archive_tasks, auto_archive};
+use rtask::projects::show_projects;
+use rtask::query::{select_targets, Query};
+use rtask::tags::{merge_tags, rename_tag, show_tags};
+use rtask::lists::{create_list, init_local_store, require_active_list, show_lists, use_list};
+use rtask::history::{show_state_report, show_task_history};
+use rtask::model::{
+    parse_list_name, parse_priority, parse_project, set_global_only, set_list_override, parse_priority_match, parse_recurrence, Priority, PriorityMatch,
+    RecurrenceRule, Status, StorageBackend,
 };
 
 fn main() {
@@ -19,19 +36,129 @@ fn main() {
         setup_config();
     }
 
-    let args = RTaskArgs::parse();
+    let args = RTaskArgs::parse_from(rewrite_tag_removals(std::env::args().collect()));
+    if args.global {
+        set_global_only();
+    }
+    if let Some(list) = args.list {
+        set_list_override(list);
+    }
+    if !matches!(
+        args.command,
+        Some(
+            Commands::ListCreate { .. }
+                | Commands::Use { .. }
+                | Commands::Lists {}
+                | Commands::Init {}
+                | Commands::Config {}
+        )
+    ) {
+        require_active_list();
+    }
+    if args.command.as_ref().is_some_and(Commands::changes_tasks) {
+        auto_archive();
+    }
     match args.command {
-        Some(Commands::Add { name, priority, due, tags }) => {
-            add_task(name, priority, due, tags);
+        Some(Commands::Add { name, priority, due, tags, project, description, repeat }) => {
+            add_task(name, priority, due, tags, project, description, repeat);
+        }
+        Some(Commands::List { query, filter, project, all, completed, status, priority, sort, search, archived, all_lists }) => {
+            let query = query_or_exit(Query::parse(&query));
+            list_tasks(ListOptions { filter, project, all, completed, status, priority, sort, search, archived, all_lists, query });
+        }
+        Some(Commands::Projects {}) => {
+            show_projects();
+        }
+        Some(Commands::Tags { command }) => {
+            match command {
+                None => show_tags(),
+                Some(TagCommands::Rename { from, to }) => rename_tag(from, to),
+                Some(TagCommands::Merge { tags, into }) => merge_tags(tags, into),
+            }
+        }
+        Some(Commands::ListCreate { name }) => {
+            create_list(name);
+        }
+        Some(Commands::Use { name }) => {
+            use_list(name);
+        }
+        Some(Commands::Lists {}) => {
+            show_lists();
+        }
+        Some(Commands::Init {}) => {
+            init_local_store();
+        }
+        Some(Commands::Archive { older_than }) => {
+            archive_tasks(older_than);
+        }
+        Some(Commands::Complete { query }) => {
+            if let Some(ids) = select_targets(&query_or_exit(Query::parse(&query)), "Complete", false, false) {
+                complete_tasks(ids);
+            }
+        }
+        Some(Commands::Start { id }) => {
+            set_task_status(id, Status::InProgress);
+        }
+        Some(Commands::Block { id }) => {
+            set_task_status(id, Status::Blocked);
+        }
+        Some(Commands::Wait { id }) => {
+            set_task_status(id, Status::Waiting);
+        }
+        Some(Commands::Cancel { id }) => {
+            set_task_status(id, Status::Cancelled);
+        }
+        Some(Commands::Reopen { id }) => {
+            reopen_task(id);
+        }
+        Some(Commands::Modify { target, editor: true, .. }) => {
+            match query_or_exit(Query::parse_text(&target)).ids() {
+                Some(&[id]) => edit_task_in_editor(id),
+                _ => println!("{}", "--editor edits one task at a time; give its ID".red().bold()),
+            }
+        }
+        Some(Commands::Modify { target, name, priority, due, no_due, tags, remove_tags, project, no_project, description, series, .. }) => {
+            let mut changes = TaskChanges {
+                name,
+                priority,
+                due: if no_due { Some(None) } else { due.map(Some) },
+                project: if no_project { Some(None) } else { project.map(Some) },
+                description: description.map(|d| Some(d).filter(|d| !d.trim().is_empty())),
+                ..TaskChanges::default()
+            };
+            changes.parse_tags(&tags);
+            changes.remove_tags.extend(remove_tags);
+            if changes.is_empty() {
+                println!("{}", "Nothing to change".yellow());
+            } else if let Some(ids) = select_targets(&query_or_exit(Query::parse_text(&target)), "Modify", false, false) {
+                modify_tasks(ids, changes, series);
+            }
+        }
+        Some(Commands::Annotate { id, text }) => {
+            annotate_task(id, text.join(" "));
         }
-        Some(Commands::List { filter, all, completed }) => {
-            list_tasks(filter, all, completed);
+        Some(Commands::Remove { query }) => {
+            let query = query_or_exit(Query::parse(&query));
+            match query.ids() {
+                Some(&[id]) => remove_task(id),
+                _ => {
+                    if let Some(ids) = select_targets(&query, "Remove", false, true) {
+                        remove_tasks(ids);
+                    }
+                }
+            }
+        }
+        Some(Commands::History { id: Some(id) }) => {
+            show_task_history(id);
+        }
+        Some(Commands::History { id: None }) => {
+            show_state_report();
         }
-        Some(Commands::Complete { id }) => {
-            complete_task(id);
+        Some(Commands::Undo {}) => {
+            undo();
         }
-        Some(Commands::Remove { id }) => {
-            remove_task(id);
+        Some(Commands::Redo {}) => {
+            redo();
         }
         Some(Commands::Show { id }) => {
             show_task_details(id);
@@ -39,23 +166,72 @@ fn main() {
         Some(Commands::Config {}) => {
             setup_config();
         }
-        Some(Commands::Today {}) => {
-            show_today_tasks();
+        Some(Commands::Doctor {}) => {
+            run_doctor();
+        }
+        Some(Commands::Migrate { to }) => {
+            migrate_store(to);
+        }
+        Some(Commands::Today { query }) => {
+            show_today_tasks(query_or_exit(Query::parse(&query)));
+        }
+        Some(Commands::Export { query }) => {
+            export_tasks(query_or_exit(Query::parse(&query)));
         }
         Some(Commands::Progress { id, percentage }) => {
             update_task_progress(id, percentage);
         }
         Some(Commands::Subtask { command }) => {
-            match command {
-                SubtaskCommands::Add { task_id, name } => {
-                    add_subtask(task_id, name);
+            let result = match command {
+                SubtaskCommands::Add { target, name, task_id, priority, due } => {
+                    let parent = match (task_id, name) {
+                        (Some(task_id), None) => Ok((SubtaskPath { task_id, indices: Vec::new() }, target)),
+                        (Some(_), Some(_)) => Err("Give either --task-id or a path, not both".to_string()),
+                        (None, Some(name)) => parse_subtask_path(&target).map(|path| (path, name)),
+                        (None, None) => Err("Missing the subtask name".to_string()),
+                    };
+                    parent.map(|(parent, name)| add_subtask(parent, name, priority, due))
+                }
+                SubtaskCommands::Toggle { path, task_id, subtask_index } => {
+                    subtask_target(path, task_id, subtask_index).map(toggle_subtask)
                 }
-                SubtaskCommands::Toggle { task_id, subtask_index } => {
-                    toggle_subtask(task_id, subtask_index - 1); // Convert to 0-based index
+                SubtaskCommands::Remove { path, task_id, subtask_index } => {
+                    subtask_target(path, task_id, subtask_index).map(remove_subtask)
                 }
-                SubtaskCommands::Remove { task_id, subtask_index } => {
-                    remove_subtask(task_id, subtask_index - 1); // Convert to 0-based index
+                SubtaskCommands::Promote { path } => {
+                    if path.indices.is_empty() {
+                        Err("Give a subtask path, e.g. 4.2".to_string())
+                    } else {
+                        promote_subtask(path);
+                        Ok(())
+                    }
                 }
+                SubtaskCommands::Demote { id, into } => {
+                    demote_task(id, into);
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                println!("{}", e.red().bold());
+            }
+        }
+        Some(Commands::Recur { command }) => {
+            match command {
+                RecurCommands::List => list_series(),
+                RecurCommands::Set { id, rule } => set_series_rule(id, rule),
+                RecurCommands::Stop { id } => stop_series(id),
+            }
+        }
+        Some(Commands::Dep { command }) => {
+            match command {
+                DepCommands::Add { id, prerequisites } => add_dependencies(id, prerequisites),
+                DepCommands::Remove { id, prerequisites } => remove_dependencies(id, prerequisites),
+            }
+        }
+        Some(Commands::Backup { command }) => {
+            match command {
+                BackupCommands::List => list_backups(),
+                BackupCommands::Restore { id } => restore_backup(id),
             }
         }
         None => {
@@ -64,6 +240,123 @@ fn main() {
     }
 }
 
+impl Commands {
+    /// Whether the command edits tasks, and so may also archive old ones
+    /// first. Reading, exporting, undoing and store maintenance never do.
+    fn changes_tasks(&self) -> bool {
+        match self {
+            Commands::Tags { command } => command.is_some(),
+            Commands::Recur { command } => !matches!(command, RecurCommands::List),
+            Commands::Add { .. }
+            | Commands::Complete { .. }
+            | Commands::Start { .. }
+            | Commands::Block { .. }
+            | Commands::Wait { .. }
+            | Commands::Cancel { .. }
+            | Commands::Reopen { .. }
+            | Commands::Modify { .. }
+            | Commands::Annotate { .. }
+            | Commands::Remove { .. }
+            | Commands::Progress { .. }
+            | Commands::Subtask { .. }
+            | Commands::Dep { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The parsed filter given to a command, or explains what is wrong with
+/// it and exits.
+fn query_or_exit(parsed: Result<Query, String>) -> Query {
+    match parsed {
+        Ok(query) => query,
+        Err(e) => {
+            println!("{} {}", "Invalid filter:".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Subtasks are addressed by path (`4.2.1`), or by the older
+/// `--task-id 4 --subtask-index 2` pair.
+fn subtask_target(
+    path: Option<SubtaskPath>,
+    task_id: Option<usize>,
+    subtask_index: Option<String>,
+) -> Result<SubtaskPath, String> {
+    match (path, task_id, subtask_index) {
+        (Some(path), _, _) if path.indices.is_empty() => {
+            Err(format!("{} is a task, not a subtask; use a path like {}.1", path, path))
+        }
+        (Some(path), _, _) => Ok(path),
+        (None, Some(task_id), Some(index)) => path_from_flags(task_id, &index),
+        _ => Err("Give a subtask path like 4.2.1".to_string()),
+    }
+}
+
+/// `modify 3 -urgent` removes a tag, but clap would read `-urgent` as a
+/// bundle of short flags. Rewrite such words after the `modify` command to
+/// the hidden `--remove-tag` option before parsing. Only the word in the
+/// subcommand position counts, so `annotate 2 edit -x` is left alone. The
+/// options of `modify` come from its clap definition, so the value after
+/// any of them (`--description -foo`) is never taken for a tag.
+fn rewrite_tag_removals(args: Vec<String>) -> Vec<String> {
+    let mut command = RTaskArgs::command();
+    command.build();
+    let Some(modify) = command.find_subcommand("modify") else {
+        return args;
+    };
+
+    let mut value_flags = Vec::new();
+    let mut flags = Vec::new();
+    for arg in modify.get_arguments() {
+        let names: Vec<String> = arg
+            .get_short_and_visible_aliases()
+            .into_iter()
+            .flatten()
+            .map(|c| format!("-{}", c))
+            .chain(arg.get_long_and_visible_aliases().into_iter().flatten().map(|l| format!("--{}", l)))
+            .collect();
+        if arg.get_action().takes_values() {
+            value_flags.extend(names.iter().cloned());
+        }
+        flags.extend(names);
+    }
+
+    // Skip the global options to find the subcommand.
+    let mut start = 1;
+    while let Some(arg) = args.get(start) {
+        match arg.as_str() {
+            "-L" | "--list" => start += 2,
+            "--global" => start += 1,
+            a if a.starts_with("--list=") || (a.starts_with("-L") && a.len() > 2) => start += 1,
+            _ => break,
+        }
+    }
+    let is_modify = args.get(start).is_some_and(|a| {
+        a == modify.get_name() || modify.get_all_aliases().any(|alias| alias == a)
+    });
+    if !is_modify {
+        return args;
+    }
+
+    // The word after `modify` picks the tasks, e.g. `-blocked`.
+    let start = (start + 1).min(args.len() - 1);
+    let mut rewritten = args[..=start].to_vec();
+    let mut previous = String::new();
+    for arg in &args[start + 1..] {
+        let is_value = value_flags.contains(&previous);
+        let is_flag = arg.starts_with("--") || flags.contains(arg);
+        if !is_value && !is_flag && arg.len() > 1 && arg.starts_with('-') {
+            rewritten.push(format!("--remove-tag={}", &arg[1..]));
+        } else {
+            rewritten.push(arg.clone());
+        }
+        previous = arg.clone();
+    }
+    rewritten
+}
+
 #[derive(Parser)]
 #[command(
     name = "rtask",
@@ -73,6 +366,14 @@ fn main() {
     long_about = "RTask - A beautiful CLI task tracker written in Rust"
 )]
 struct RTaskArgs {
+    /// Use this task list instead of the current one
+    #[arg(short = 'L', long = "list", global = true, value_name = "LIST", value_parser = parse_list_name)]
+    list: Option<String>,
+    
+    /// Use the global task store even inside a directory with a .rtask/ store
+    #[arg(long, global = true)]
+    global: bool,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -82,40 +383,198 @@ enum Commands {
     #[command(visible_alias = "a")]
     Add {
         name: String,
-        #[arg(short, long, default_value = "medium")]
-        priority: String,
+        #[arg(short, long, value_parser = parse_priority)]
+        priority: Option<Priority>,
         #[arg(short, long)]
         due: Option<String>,
         
         #[arg(short, long)]
         tags: Option<String>,
+        
+        /// Project, with dots for sub-projects, e.g. acme.backend
+        #[arg(short = 'P', long, value_parser = parse_project)]
+        project: Option<String>,
+        
+        #[arg(long)]
+        description: Option<String>,
+        
+        #[arg(short, long, value_parser = parse_recurrence)]
+        repeat: Option<RecurrenceRule>,
     },
     
     #[command(visible_alias = "ls")]
     #[command(visible_alias = "log")]
     List {
-        #[arg(short, long)]
-        filter: Option<String>,
+        /// Filter terms, e.g. status:pending +backend due.before:friday name~migr
+        #[arg(allow_hyphen_values = true)]
+        query: Vec<String>,
+        
+        /// Tags to match exactly (`work`, `work*` for a prefix); `a,b` for
+        /// either, `-tag:someday` to leave a tag out; repeat for all of them
+        #[arg(short, long, visible_alias = "tag", allow_hyphen_values = true)]
+        filter: Vec<String>,
+        
+        /// Only tasks in this project or a project below it
+        #[arg(short = 'P', long, value_parser = parse_project)]
+        project: Option<String>,
         
         #[arg(short, long)]
         all: bool,
         
         #[arg(short, long)]
         completed: bool,
+        
+        #[arg(short, long, value_enum)]
+        status: Option<Status>,
+        
+        #[arg(short, long, value_parser = parse_priority_match)]
+        priority: Option<PriorityMatch>,
+        
+        #[arg(long, value_enum, default_value_t = SortKey::Id)]
+        sort: SortKey,
+        
+        #[arg(short = 'S', long)]
+        search: Option<String>,
+        
+        /// List archived tasks instead of the active ones
+        #[arg(long, conflicts_with_all = ["all", "completed"])]
+        archived: bool,
+        
+        /// List tasks from every list, with a list column
+        #[arg(long, conflicts_with = "archived")]
+        all_lists: bool,
+    },
+    
+    /// Create a new named task list
+    ListCreate {
+        #[arg(value_parser = parse_list_name)]
+        name: String,
+    },
+    
+    /// Switch the list that commands use
+    Use {
+        #[arg(value_parser = parse_list_name)]
+        name: String,
+    },
+    
+    /// Show every task list and how many tasks are open in each
+    Lists {},
+    
+    /// Show task counts and average progress for each project
+    Projects {},
+    
+    /// Show every tag with how many tasks use it, or rename and merge tags
+    Tags {
+        #[command(subcommand)]
+        command: Option<TagCommands>,
+    },
+    
+    /// Create a .rtask/ task store for this directory and everything below it
+    Init {},
+    
+    /// Move done and cancelled tasks to the yearly archive files
+    Archive {
+        /// Only tasks closed at least this many days ago
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
     },
     
     #[command(visible_alias = "done")]
     #[command(visible_alias = "c")]
     Complete {
+        /// Task ID(s), or filter terms picking the tasks
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    
+    Start {
+        id: usize,
+    },
+    
+    Block {
+        id: usize,
+    },
+    
+    Wait {
+        id: usize,
+    },
+    
+    Cancel {
+        id: usize,
+    },
+    
+    #[command(visible_alias = "undone")]
+    Reopen {
         id: usize,
     },
     
+    #[command(visible_alias = "edit")]
+    #[command(visible_alias = "mod")]
+    Modify {
+        /// Task ID, or filter terms in quotes picking the tasks
+        #[arg(allow_hyphen_values = true)]
+        target: String,
+        
+        #[arg(short, long, allow_hyphen_values = true)]
+        name: Option<String>,
+        
+        #[arg(short, long, value_parser = parse_priority)]
+        priority: Option<Priority>,
+        
+        #[arg(short, long, conflicts_with = "no_due")]
+        due: Option<String>,
+        
+        #[arg(long)]
+        no_due: bool,
+        
+        tags: Vec<String>,
+        
+        #[arg(long = "remove-tag", hide = true)]
+        remove_tags: Vec<String>,
+        
+        #[arg(short = 'P', long, value_parser = parse_project, conflicts_with = "no_project")]
+        project: Option<String>,
+        
+        #[arg(long)]
+        no_project: bool,
+        
+        #[arg(long, allow_hyphen_values = true)]
+        description: Option<String>,
+        
+        #[arg(long)]
+        series: bool,
+        
+        #[arg(long, conflicts_with_all = ["name", "priority", "due", "no_due", "tags", "remove_tags", "project", "no_project", "description"])]
+        editor: bool,
+    },
+    
+    #[command(visible_alias = "note")]
+    Annotate {
+        id: usize,
+        
+        #[arg(required = true, num_args = 1..)]
+        text: Vec<String>,
+    },
+    
     #[command(visible_alias = "rm")]
     #[command(visible_alias = "delete")]
     Remove {
-        id: usize,
+        /// Task ID(s), or filter terms picking the tasks
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    
+    /// Show every change to a task, or time spent in each state overall
+    History {
+        id: Option<usize>,
     },
     
+    /// Revert the last change to the tasks
+    Undo {},
+    
+    /// Re-apply the last undone change
+    Redo {},
+    
     #[command(visible_alias = "s")]
     Show {
         id: usize,
@@ -124,8 +583,26 @@ enum Commands {
     #[command(visible_alias = "cfg")]
     Config {},
     
+    Doctor {},
+
+    Migrate {
+        #[arg(long, value_enum)]
+        to: StorageBackend,
+    },
+
     #[command(visible_alias = "td")]
-    Today {},
+    Today {
+        /// Filter terms narrowing down today's tasks
+        #[arg(allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    
+    /// Print the tasks matching a filter as JSON
+    Export {
+        /// Filter terms; every task if none
+        #[arg(allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
 
     #[command(visible_alias = "prog")]
     #[command(visible_alias = "p")]
@@ -139,33 +616,191 @@ enum Commands {
         #[command(subcommand)]
         command: SubtaskCommands,
     },
+    
+    #[command(visible_alias = "repeat")]
+    Recur {
+        #[command(subcommand)]
+        command: RecurCommands,
+    },
+    
+    #[command(visible_alias = "deps")]
+    Dep {
+        #[command(subcommand)]
+        command: DepCommands,
+    },
+    
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagCommands {
+    /// Rename a tag on every task; merges it if the new name is in use
+    #[command(visible_alias = "mv")]
+    Rename {
+        from: String,
+        to: String,
+    },
+    
+    /// Replace several tags with one
+    Merge {
+        #[arg(required = true, num_args = 1..)]
+        tags: Vec<String>,
+        
+        #[arg(long)]
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    #[command(visible_alias = "ls")]
+    List,
+    
+    Restore {
+        /// Backup ID, or its number in `rtask backup list`
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DepCommands {
+    #[command(visible_alias = "a")]
+    Add {
+        id: usize,
+        #[arg(required = true, num_args = 1..)]
+        prerequisites: Vec<usize>,
+    },
+    
+    #[command(visible_alias = "rm")]
+    Remove {
+        id: usize,
+        #[arg(required = true, num_args = 1..)]
+        prerequisites: Vec<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecurCommands {
+    #[command(visible_alias = "ls")]
+    List,
+    
+    Set {
+        id: usize,
+        #[arg(value_parser = parse_recurrence)]
+        rule: RecurrenceRule,
+    },
+    
+    Stop {
+        id: usize,
+    },
 }
 
 #[derive(Subcommand)]
 enum SubtaskCommands {
     #[command(visible_alias = "a")]
     Add {
+        /// Task ID or subtask path to add under, e.g. 4 or 4.2
+        target: String,
+        name: Option<String>,
+        
         #[arg(short, long)]
-        task_id: usize,
-        name: String,
+        task_id: Option<usize>,
+        
+        #[arg(short, long, value_parser = parse_priority)]
+        priority: Option<Priority>,
+        
+        #[arg(short, long)]
+        due: Option<String>,
     },
     
     #[command(visible_alias = "t")]
     #[command(visible_alias = "check")]
     Toggle {
-        #[arg(short, long)]
-        task_id: usize,
+        /// Subtask path, e.g. 4.2.1
+        #[arg(value_parser = parse_subtask_path, required_unless_present = "task_id")]
+        path: Option<SubtaskPath>,
+        
+        #[arg(short, long, requires = "subtask_index", conflicts_with = "path")]
+        task_id: Option<usize>,
         
         #[arg(short, long)]
-        subtask_index: usize,
+        subtask_index: Option<String>,
     },
     
     #[command(visible_alias = "rm")]
     Remove {
-        #[arg(short, long)]
-        task_id: usize,
+        /// Subtask path, e.g. 4.2.1
+        #[arg(value_parser = parse_subtask_path, required_unless_present = "task_id")]
+        path: Option<SubtaskPath>,
+        
+        #[arg(short, long, requires = "subtask_index", conflicts_with = "path")]
+        task_id: Option<usize>,
         
         #[arg(short, long)]
-        subtask_index: usize,
+        subtask_index: Option<String>,
+    },
+    
+    /// Turn a subtask into a task of its own
+    Promote {
+        /// Subtask path, e.g. 4.2
+        #[arg(value_parser = parse_subtask_path)]
+        path: SubtaskPath,
     },
-}
\ No newline at end of file
+    
+    /// Move a task into another task's subtasks
+    Demote {
+        id: usize,
+        
+        /// Task ID or subtask path to nest it under, e.g. 4 or 4.2
+        #[arg(value_parser = parse_subtask_path)]
+        into: SubtaskPath,
+    },
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(line: &str) -> Vec<String> {
+        rewrite_tag_removals(line.split(' ').map(str::to_string).collect())
+    }
+
+    fn modify(line: &str) -> (Option<String>, Option<String>, Vec<String>) {
+        match RTaskArgs::try_parse_from(rewrite(line)).unwrap().command {
+            Some(Commands::Modify { name, description, remove_tags, .. }) => (name, description, remove_tags),
+            _ => panic!("not a modify command"),
+        }
+    }
+
+    #[test]
+    fn rewrites_tag_removals_after_modify() {
+        assert_eq!(rewrite("rtask modify 3 -urgent +work")[3], "--remove-tag=urgent");
+        assert_eq!(rewrite("rtask -L work edit -blocked -x")[4..], ["-blocked", "--remove-tag=x"]);
+        assert_eq!(rewrite("rtask annotate 2 edit -x")[4], "-x");
+        assert_eq!(modify("rtask mod 3 -p high -someday").2, vec!["someday"]);
+    }
+
+    #[test]
+    fn keeps_option_values_that_start_with_a_dash() {
+        let (name, description, removed) = modify("rtask modify 3 --description -foo -n -bar -old");
+        assert_eq!(description.as_deref(), Some("-foo"));
+        assert_eq!(name.as_deref(), Some("-bar"));
+        assert_eq!(removed, vec!["old"]);
+    }
+
+    #[test]
+    fn only_commands_that_edit_tasks_archive_first() {
+        let changes = |line: &str| {
+            let args = RTaskArgs::try_parse_from(rewrite(line)).unwrap();
+            args.command.as_ref().is_some_and(Commands::changes_tasks)
+        };
+        for line in ["rtask add x", "rtask done 1", "rtask tags rename a b", "rtask recur stop 2"] {
+            assert!(changes(line), "{}", line);
+        }
+        for line in ["rtask", "rtask ls", "rtask show 1", "rtask history 1", "rtask today", "rtask tags", "rtask doctor", "rtask recur list"] {
+            assert!(!changes(line), "{}", line);
+        }
+    }
+}
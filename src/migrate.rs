@@ -0,0 +1,278 @@
+use chrono::Local;
+use colored::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::print_store_error;
+use crate::model::{StorageBackend, get_list_dir, list_names, load_config, save_config};
+use crate::store::{StoreError, backend_files, open_backend_in};
+
+/// Copies every task, in every list, from the configured backend into
+/// `target` and makes `target` the configured backend. The old store's
+/// files are then renamed to `<file>.<timestamp>.migrated`: kept as a
+/// fallback, but out of the way of a later migration back. Refuses to
+/// overwrite a target that already holds other tasks.
+pub fn migrate_store(target: StorageBackend) {
+    let mut config = load_config();
+
+    if config.backend == target {
+        println!("{}", format!("Already using the {:?} backend", target).yellow());
+        return;
+    }
+
+    // Every list shares the backend, so they all move together.
+    let lists: Vec<(String, PathBuf)> = list_names()
+        .into_iter()
+        .map(|name| {
+            let dir = get_list_dir(&name);
+            (name, dir)
+        })
+        .collect();
+    let source = config.backend;
+    let mut migrated = Vec::new();
+    let result = check_targets(source, target, &lists).and_then(|()| {
+        copy_lists(source, target, &lists, &mut migrated, &mut || {
+            config.backend = target;
+            save_config(&config)
+                .map_err(|e| StoreError::Backend(format!("couldn't save the configuration: {}", e)))
+        })
+    });
+
+    match result {
+        Ok(()) => {
+            println!(
+                "{} {}",
+                format!("✓ Migrated {} lists to", migrated.len()).green().bold(),
+                format!("{:?}", target).bright_white()
+            );
+            let dirs: Vec<PathBuf> = lists.into_iter().map(|(_, dir)| dir).collect();
+            match set_aside(source, &dirs) {
+                Ok(kept) if kept.is_empty() => {}
+                Ok(kept) => {
+                    println!("{}", format!("The {:?} store was kept as:", source).cyan());
+                    for path in kept {
+                        println!("  {}", path.display());
+                    }
+                }
+                Err(e) => println!(
+                    "{} {}",
+                    format!("Couldn't set the old {:?} store aside:", source).yellow().bold(),
+                    e
+                ),
+            }
+        }
+        Err(e) => {
+            print_store_error("Error migrating tasks:", &e);
+            if !migrated.is_empty() {
+                println!(
+                    "{}",
+                    format!(
+                        "Already copied to {:?}: {}. rtask keeps using {:?}; run the migration again to finish",
+                        target,
+                        migrated.join(", "),
+                        source
+                    )
+                    .yellow()
+                );
+            }
+        }
+    }
+}
+
+/// Checks every list up front, so a target that already holds other tasks
+/// stops the migration before anything is copied. A target whose copy
+/// already matches, from an earlier run that was cut short, is fine.
+fn check_targets(
+    source: StorageBackend,
+    target: StorageBackend,
+    lists: &[(String, PathBuf)],
+) -> Result<(), StoreError> {
+    for (name, dir) in lists {
+        let from = open_backend_in(source, dir)?;
+        from.check_schema()?;
+        let data = from.load()?;
+        let existing = open_backend_in(target, dir)?.load()?;
+        if !existing.tasks.is_empty() && serde_json::to_value(&existing)? != serde_json::to_value(&data)? {
+            return Err(StoreError::Rejected(format!(
+                "The {:?} store of list '{}' already contains {} tasks; not overwriting it",
+                target,
+                name,
+                existing.tasks.len()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Copies each list to `target` inside a transaction on its source, so
+/// every source stays locked until all of them are copied and `finish` has
+/// switched the configuration over: a change made meanwhile waits for the
+/// lock rather than landing in a store that is being left behind. The
+/// source transactions change nothing, so nothing is written back. Lists
+/// are added to `migrated` as they are copied, to report a partial run.
+fn copy_lists(
+    source: StorageBackend,
+    target: StorageBackend,
+    lists: &[(String, PathBuf)],
+    migrated: &mut Vec<String>,
+    finish: &mut dyn FnMut() -> Result<(), StoreError>,
+) -> Result<(), StoreError> {
+    let Some(((name, dir), rest)) = lists.split_first() else {
+        return finish();
+    };
+    let from = open_backend_in(source, dir)?;
+    from.check_schema()?;
+    let destination = open_backend_in(target, dir)?;
+
+    from.transaction(&mut |data| {
+        destination.transaction(&mut |existing| {
+            if serde_json::to_value(&*existing)? == serde_json::to_value(&*data)? {
+                return Ok(());
+            }
+            if !existing.tasks.is_empty() {
+                return Err(StoreError::Rejected(format!(
+                    "The {:?} store of list '{}' already contains {} tasks; not overwriting it",
+                    target,
+                    name,
+                    existing.tasks.len()
+                )));
+            }
+            *existing = data.clone();
+            Ok(())
+        })?;
+        migrated.push(name.clone());
+        copy_lists(source, target, rest, migrated, finish)
+    })
+}
+
+/// Renames the files `backend` kept in each of `dirs` to
+/// `<file>.<timestamp>.migrated` and returns the new paths.
+fn set_aside(backend: StorageBackend, dirs: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut kept = Vec::new();
+    for path in dirs.iter().flat_map(|dir| backend_files(backend, dir)) {
+        if !path.exists() {
+            continue;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let copy = path.with_file_name(format!("{}.{}.migrated", file_name, stamp));
+        fs::rename(&path, &copy)?;
+        kept.push(copy);
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::{task, temp_dir};
+    use crate::model::TaskData;
+    use std::fs::{File, TryLockError};
+    use std::path::Path;
+
+    fn lists(dirs: &[&PathBuf]) -> Vec<(String, PathBuf)> {
+        dirs.iter().enumerate().map(|(n, dir)| (format!("list{}", n), dir.to_path_buf())).collect()
+    }
+
+    fn fill(backend: StorageBackend, dir: &Path, names: &[&str]) {
+        let store = open_backend_in(backend, dir).unwrap();
+        for name in names {
+            store.insert(task(0, name)).unwrap();
+        }
+    }
+
+    fn names(backend: StorageBackend, dir: &Path) -> Vec<String> {
+        let data: TaskData = open_backend_in(backend, dir).unwrap().load().unwrap();
+        data.tasks.into_iter().map(|t| t.name).collect()
+    }
+
+    fn migrate(from: StorageBackend, to: StorageBackend, lists: &[(String, PathBuf)]) -> Result<Vec<String>, StoreError> {
+        let mut migrated = Vec::new();
+        check_targets(from, to, lists)?;
+        copy_lists(from, to, lists, &mut migrated, &mut || Ok(()))?;
+        Ok(migrated)
+    }
+
+    #[test]
+    fn copies_every_list_and_can_migrate_back() {
+        let (a, b) = (temp_dir(), temp_dir());
+        fill(StorageBackend::Json, &a, &["one", "two"]);
+        fill(StorageBackend::Json, &b, &["three"]);
+        let all = lists(&[&a, &b]);
+
+        let migrated = migrate(StorageBackend::Json, StorageBackend::Events, &all).unwrap();
+        assert_eq!(migrated, vec!["list0", "list1"]);
+        assert_eq!(names(StorageBackend::Events, &a), vec!["one", "two"]);
+        let kept = set_aside(StorageBackend::Json, &[a.clone(), b.clone()]).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert!(!a.join("tasks.json").exists());
+
+        // Work goes on in the new backend, then everything moves back.
+        fill(StorageBackend::Events, &a, &["four"]);
+        migrate(StorageBackend::Events, StorageBackend::Json, &all).unwrap();
+        assert_eq!(names(StorageBackend::Json, &a), vec!["one", "two", "four"]);
+        fs::remove_dir_all(a).unwrap();
+        fs::remove_dir_all(b).unwrap();
+    }
+
+    #[test]
+    fn refuses_a_target_holding_other_tasks_before_copying_anything() {
+        let (a, b) = (temp_dir(), temp_dir());
+        fill(StorageBackend::Json, &a, &["one"]);
+        fill(StorageBackend::Json, &b, &["two"]);
+        fill(StorageBackend::Events, &b, &["other"]);
+
+        let result = migrate(StorageBackend::Json, StorageBackend::Events, &lists(&[&a, &b]));
+        assert!(matches!(result, Err(StoreError::Rejected(_))));
+        assert!(names(StorageBackend::Events, &a).is_empty());
+        fs::remove_dir_all(a).unwrap();
+        fs::remove_dir_all(b).unwrap();
+    }
+
+    #[test]
+    fn a_retry_skips_lists_that_were_already_copied() {
+        let a = temp_dir();
+        fill(StorageBackend::Json, &a, &["one"]);
+        let all = lists(&[&a]);
+        migrate(StorageBackend::Json, StorageBackend::Events, &all).unwrap();
+        migrate(StorageBackend::Json, StorageBackend::Events, &all).unwrap();
+        assert_eq!(names(StorageBackend::Events, &a), vec!["one"]);
+        fs::remove_dir_all(a).unwrap();
+    }
+
+    #[test]
+    fn keeps_every_source_locked_until_the_switch() {
+        let (a, b) = (temp_dir(), temp_dir());
+        fill(StorageBackend::Json, &a, &["one"]);
+        fill(StorageBackend::Json, &b, &["two"]);
+
+        let mut checked = 0;
+        copy_lists(StorageBackend::Json, StorageBackend::Events, &lists(&[&a, &b]), &mut Vec::new(), &mut || {
+            for dir in [&a, &b] {
+                let lock = File::open(dir.join("tasks.json.lock")).unwrap();
+                assert!(matches!(lock.try_lock(), Err(TryLockError::WouldBlock)));
+                checked += 1;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(checked, 2);
+        fs::remove_dir_all(a).unwrap();
+        fs::remove_dir_all(b).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn moves_to_sqlite_and_back() {
+        let a = temp_dir();
+        fill(StorageBackend::Json, &a, &["one"]);
+        let all = lists(&[&a]);
+
+        migrate(StorageBackend::Json, StorageBackend::Sqlite, &all).unwrap();
+        set_aside(StorageBackend::Json, std::slice::from_ref(&a)).unwrap();
+        fill(StorageBackend::Sqlite, &a, &["two"]);
+        migrate(StorageBackend::Sqlite, StorageBackend::Json, &all).unwrap();
+        assert_eq!(names(StorageBackend::Json, &a), vec!["one", "two"]);
+        fs::remove_dir_all(a).unwrap();
+    }
+}
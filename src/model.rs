@@ -1,27 +1,336 @@
-use serde::{Deserialize, Serialize};
+use chrono::{Datelike, Days, Local, NaiveDate, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 use tabled::Tabled;
-use dirs;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Tabled)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    #[default]
+    Pending,
+    InProgress,
+    Blocked,
+    Waiting,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    pub const ALL: [Status; 6] = [
+        Status::Pending,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Waiting,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pending => "pending",
+            Status::InProgress => "in-progress",
+            Status::Blocked => "blocked",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+        }
+    }
+
+    /// Done and cancelled tasks need no more work and are hidden by default.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    /// Accepts the canonical names case-insensitively, plus the spellings
+    /// older versions and hand edits tend to use.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_lowercase().replace(['_', ' '], "-");
+        match normalized.as_str() {
+            "pending" | "todo" | "open" => Ok(Status::Pending),
+            "in-progress" | "inprogress" | "started" | "doing" => Ok(Status::InProgress),
+            "blocked" => Ok(Status::Blocked),
+            "waiting" => Ok(Status::Waiting),
+            "done" | "complete" | "completed" => Ok(Status::Done),
+            "cancelled" | "canceled" => Ok(Status::Cancelled),
+            _ => Err(format!("unknown status \"{}\"", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Status {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A task's priority, stored as the name of a level in the configured
+/// [`PriorityScale`]. Values outside the current scale (e.g. after the
+/// scale was changed) still load; they just sort after every known level.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct Priority(String);
+
+impl Priority {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Wraps a stored value without checking it against the scale.
+impl From<String> for Priority {
+    fn from(value: String) -> Self {
+        Priority(value)
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub fn default_priority_levels() -> Vec<String> {
+    vec!["high".to_string(), "medium".to_string(), "low".to_string()]
+}
+
+/// The ordered priority levels from `AppConfig.priorities`, most urgent
+/// first. The default is high/medium/low; a numeric scale like P0–P4 or
+/// any other list can be configured instead.
+#[derive(Debug, Clone)]
+pub struct PriorityScale {
+    levels: Vec<String>,
+}
+
+impl PriorityScale {
+    pub fn new(levels: Vec<String>) -> Self {
+        let levels: Vec<String> = levels
+            .into_iter()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect();
+        if levels.is_empty() {
+            return Self::new(default_priority_levels());
+        }
+        Self { levels }
+    }
+
+    pub fn load() -> Self {
+        Self::new(load_config().priorities)
+    }
+
+    pub fn levels(&self) -> &[String] {
+        &self.levels
+    }
+
+    /// Matches `input` against the scale case-insensitively and returns
+    /// the level with its configured spelling.
+    pub fn parse(&self, input: &str) -> Result<Priority, String> {
+        self.levels
+            .iter()
+            .find(|level| level.eq_ignore_ascii_case(input.trim()))
+            .map(|level| Priority(level.clone()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown priority \"{}\" (expected one of: {})",
+                    input,
+                    self.levels.join(", ")
+                )
+            })
+    }
+
+    /// Position in the scale, 0 being the most urgent.
+    pub fn rank(&self, priority: &Priority) -> Option<usize> {
+        self.levels
+            .iter()
+            .position(|level| level.eq_ignore_ascii_case(priority.as_str()))
+    }
+
+    /// The configured default, or the middle of the scale when that isn't
+    /// a valid level.
+    pub fn default_priority(&self, config: &AppConfig) -> Priority {
+        self.parse(&config.default_priority)
+            .unwrap_or_else(|_| Priority(self.levels[self.levels.len() / 2].clone()))
+    }
+}
+
+/// Clap value parser for priorities, validated against the configured scale.
+pub fn parse_priority(input: &str) -> Result<Priority, String> {
+    PriorityScale::load().parse(input)
+}
+
+/// Clap value parser for project paths: dot-separated names such as
+/// `acme.backend.auth`, without spaces or empty parts.
+pub fn parse_project(input: &str) -> Result<String, String> {
+    let project = input.trim();
+    let valid = !project.is_empty()
+        && project
+            .split('.')
+            .all(|part| !part.is_empty() && !part.chars().any(char::is_whitespace));
+    if valid {
+        Ok(project.to_string())
+    } else {
+        Err(format!(
+            "Invalid project '{}'. Use dot-separated names like acme.backend.auth",
+            input
+        ))
+    }
+}
+
+/// Whether `project` is `ancestor` or a project below it: `acme` holds
+/// `acme.backend` but not `acme-corp`.
+pub fn in_project(project: &str, ancestor: &str) -> bool {
+    project
+        .strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// A priority filter: one level, or with a trailing `+` that level and
+/// everything more urgent (e.g. `medium+`).
+#[derive(Debug, Clone)]
+pub struct PriorityMatch {
+    pub priority: Priority,
+    pub or_higher: bool,
+}
+
+impl PriorityMatch {
+    pub fn levels(&self, scale: &PriorityScale) -> Vec<Priority> {
+        match (self.or_higher, scale.rank(&self.priority)) {
+            (true, Some(rank)) => scale.levels[..=rank]
+                .iter()
+                .map(|level| Priority(level.clone()))
+                .collect(),
+            _ => vec![self.priority.clone()],
+        }
+    }
+}
+
+/// Clap value parser for [`PriorityMatch`].
+pub fn parse_priority_match(input: &str) -> Result<PriorityMatch, String> {
+    let (name, or_higher) = match input.trim().strip_suffix('+') {
+        Some(name) => (name, true),
+        None => (input, false),
+    };
+    Ok(PriorityMatch {
+        priority: parse_priority(name)?,
+        or_higher,
+    })
+}
+
+/// A node in a task's subtask tree. IDs are unique within the task and
+/// survive reordering; paths like `2.1` are positions and do not.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct SubTask {
+    #[serde(default)]
+    pub id: usize,
     pub name: String,
     pub completed: bool,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub subtasks: Vec<SubTask>,
+}
+
+impl SubTask {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// 100 or 0 for a leaf; the average of the children otherwise.
+    pub fn progress(&self) -> u8 {
+        if self.subtasks.is_empty() {
+            return if self.completed { 100 } else { 0 };
+        }
+        average_progress(&self.subtasks)
+    }
+
+    /// Marks this node and everything below it.
+    pub fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+        for child in &mut self.subtasks {
+            child.set_completed(completed);
+        }
+    }
+
+    /// A node with children counts as completed exactly when all of them
+    /// are. Works bottom-up so changes deep in the tree reach the top.
+    fn roll_up(&mut self) {
+        for child in &mut self.subtasks {
+            child.roll_up();
+        }
+        if !self.subtasks.is_empty() {
+            self.completed = self.subtasks.iter().all(|c| c.completed);
+        }
+    }
+
+    /// Every node in this subtree, parents before their children.
+    pub fn walk(&self) -> Vec<&SubTask> {
+        let mut nodes = vec![self];
+        for child in &self.subtasks {
+            nodes.extend(child.walk());
+        }
+        nodes
+    }
+
+    fn walk_mut(&mut self, f: &mut dyn FnMut(&mut SubTask)) {
+        f(self);
+        for child in &mut self.subtasks {
+            child.walk_mut(f);
+        }
+    }
+}
+
+fn average_progress(nodes: &[SubTask]) -> u8 {
+    let total: u32 = nodes.iter().map(|n| u32::from(n.progress())).sum();
+    (total / nodes.len() as u32) as u8
+}
+
+/// A timestamped note appended to a task. Annotations are never edited
+/// in place, so together they form a log of what happened to the task.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Annotation {
+    pub created_at: String,
+    pub text: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Tabled)]
 pub struct Task {
     #[tabled(rename = "ID")]
     pub id: usize,
+
+    #[tabled(skip)]
+    #[serde(default)]
+    pub uuid: Option<String>,
     
     #[tabled(rename = "Task")]
     pub name: String,
     
     #[tabled(rename = "Priority")]
-    pub priority: String,
+    pub priority: Priority,
     
     #[tabled(rename = "Status")]
-    pub status: String,
+    pub status: Status,
     
     #[tabled(rename = "Progress")]
     pub progress: u8,
@@ -33,6 +342,11 @@ pub struct Task {
     #[tabled(display_with = "display_vec_string")]
     pub tags: Vec<String>,
     
+    /// Dotted project path, e.g. `acme.backend.auth`.
+    #[tabled(skip)]
+    #[serde(default)]
+    pub project: Option<String>,
+    
     #[tabled(rename = "Created")]
     pub created_at: String,
     
@@ -41,6 +355,43 @@ pub struct Task {
     
     #[tabled(skip)]
     pub subtasks: Vec<SubTask>,
+
+    #[tabled(skip)]
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[tabled(skip)]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+
+    /// UUID of the [`Series`] this task is an occurrence of.
+    #[tabled(skip)]
+    #[serde(default)]
+    pub series: Option<String>,
+
+    /// IDs of the tasks that must be finished before this one can start.
+    #[tabled(skip)]
+    #[serde(default)]
+    pub depends_on: Vec<usize>,
+
+    /// The task this one was promoted out of, if it started as a subtask.
+    #[tabled(skip)]
+    #[serde(default)]
+    pub parent: Option<usize>,
+
+    /// Progress and checked subtasks from just before the task was
+    /// completed, put back if it is reopened.
+    #[tabled(skip)]
+    #[serde(default)]
+    pub reopen: Option<ReopenState>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ReopenState {
+    pub progress: u8,
+    /// IDs of the subtasks that were checked.
+    #[serde(default)]
+    pub completed_subtasks: Vec<usize>,
 }
 
 fn display_option_string(opt: &Option<String>) -> String {
@@ -50,7 +401,7 @@ fn display_option_string(opt: &Option<String>) -> String {
     }
 }
 
-fn display_vec_string(vec: &Vec<String>) -> String {
+fn display_vec_string(vec: &[String]) -> String {
     if vec.is_empty() {
         "-".to_string()
     } else {
@@ -58,11 +409,544 @@ fn display_vec_string(vec: &Vec<String>) -> String {
     }
 }
 
+/// Everything persisted in `tasks.json`: the tasks themselves plus the
+/// counter used to hand out IDs. IDs are never reused, so an ID someone
+/// wrote down keeps pointing at the same task even after removals.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskData {
+    pub next_id: usize,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub series: Vec<Series>,
+}
+
+impl Default for TaskData {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            tasks: Vec::new(),
+            series: Vec::new(),
+        }
+    }
+}
+
+impl TaskData {
+    /// Builds the store from a legacy bare task array, keeping the IDs the
+    /// tasks already had and starting the counter after the highest one.
+    pub fn from_legacy(tasks: Vec<Task>) -> Self {
+        let mut data = Self {
+            tasks,
+            ..Self::default()
+        };
+        data.normalize();
+        data
+    }
+
+    /// Repairs invariants that hand edits or older versions may have broken:
+    /// the counter must be past every ID in use, every task gets a UUID and
+    /// dependencies and parent links only point at other tasks that were
+    /// given out. A link to a task that is no longer here is kept, since
+    /// the task may be in the archive; [`TaskData::remove`] drops the links
+    /// to a task that is deleted.
+    pub fn normalize(&mut self) {
+        let max_id = self.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        if self.next_id <= max_id {
+            self.next_id = max_id + 1;
+        }
+        let next_id = self.next_id;
+        for task in &mut self.tasks {
+            if task.uuid.is_none() {
+                task.uuid = Some(new_uuid());
+            }
+            let id = task.id;
+            task.depends_on.retain(|dep| *dep != id && *dep < next_id);
+            task.parent = task.parent.filter(|parent| *parent != id && *parent < next_id);
+            task.assign_subtask_ids();
+        }
+    }
+
+    /// Deletes task `id` along with every dependency and parent link that
+    /// points at it, returning the task if it existed.
+    pub fn remove(&mut self, id: usize) -> Option<Task> {
+        let task = self.tasks.remove(self.position(id)?);
+        for other in &mut self.tasks {
+            other.depends_on.retain(|dep| *dep != id);
+            other.parent = other.parent.filter(|parent| *parent != id);
+        }
+        Some(task)
+    }
+
+    pub fn allocate_id(&mut self) -> usize {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        id
+    }
+
+    pub fn find(&self, id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    pub fn position(&self, id: usize) -> Option<usize> {
+        self.tasks.iter().position(|t| t.id == id)
+    }
+
+    pub fn find_series(&self, uuid: &str) -> Option<&Series> {
+        self.series.iter().find(|s| s.uuid == uuid)
+    }
+
+    pub fn find_series_mut(&mut self, uuid: &str) -> Option<&mut Series> {
+        self.series.iter_mut().find(|s| s.uuid == uuid)
+    }
+}
+
+impl Task {
+    /// Every subtask in the tree, parents before their children.
+    pub fn all_subtasks(&self) -> Vec<&SubTask> {
+        self.subtasks.iter().flat_map(|s| s.walk()).collect()
+    }
+
+    /// The subtask at a path of 0-based positions, e.g. `[1, 0]` for `2.1`.
+    pub fn subtask(&self, path: &[usize]) -> Option<&SubTask> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.subtasks.get(*first)?, |node, idx| node.subtasks.get(*idx))
+    }
+
+    pub fn subtask_mut(&mut self, path: &[usize]) -> Option<&mut SubTask> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.subtasks.get_mut(*first)?, |node, idx| node.subtasks.get_mut(*idx))
+    }
+
+    /// The children list a new subtask at `parent` goes into; the task's
+    /// own top level for an empty path.
+    pub fn subtask_children_mut(&mut self, parent: &[usize]) -> Option<&mut Vec<SubTask>> {
+        if parent.is_empty() {
+            Some(&mut self.subtasks)
+        } else {
+            self.subtask_mut(parent).map(|node| &mut node.subtasks)
+        }
+    }
+
+    pub fn remove_subtask(&mut self, path: &[usize]) -> Option<SubTask> {
+        let (last, parent) = path.split_last()?;
+        let siblings = self.subtask_children_mut(parent)?;
+        (*last < siblings.len()).then(|| siblings.remove(*last))
+    }
+
+    pub fn next_subtask_id(&self) -> usize {
+        self.all_subtasks().iter().map(|s| s.id).max().unwrap_or(0) + 1
+    }
+
+    /// Gives every subtask without an ID (older files, new nodes) the next
+    /// free one, and moves duplicates off IDs that are already taken.
+    pub fn assign_subtask_ids(&mut self) {
+        let mut next = self.next_subtask_id();
+        let mut seen = HashSet::new();
+        for subtask in &mut self.subtasks {
+            subtask.walk_mut(&mut |node| {
+                if node.id == 0 || !seen.insert(node.id) {
+                    node.id = next;
+                    seen.insert(next);
+                    next += 1;
+                }
+            });
+        }
+    }
+
+    /// Recomputes parent completion and the task's progress from the
+    /// subtask tree. Tasks without subtasks keep their manual progress.
+    pub fn roll_up_subtasks(&mut self) {
+        for subtask in &mut self.subtasks {
+            subtask.roll_up();
+        }
+        if !self.subtasks.is_empty() {
+            self.progress = average_progress(&self.subtasks);
+        }
+    }
+
+    /// The task's progress and checked subtasks as they are now.
+    pub fn reopen_state(&self) -> ReopenState {
+        ReopenState {
+            progress: self.progress,
+            completed_subtasks: self
+                .all_subtasks()
+                .into_iter()
+                .filter(|s| s.completed)
+                .map(|s| s.id)
+                .collect(),
+        }
+    }
+
+    /// Marks the task done at 100% with every subtask checked, remembering
+    /// how far along it was so [`Task::reopen`] can undo it.
+    pub fn complete(&mut self) {
+        if self.status != Status::Done {
+            self.reopen = Some(self.reopen_state());
+        }
+        self.status = Status::Done;
+        self.progress = 100;
+        self.completed_at = Some(timestamp());
+        for subtask in &mut self.subtasks {
+            subtask.set_completed(true);
+        }
+    }
+
+    /// Takes a done or cancelled task back to open work, restoring the
+    /// progress and subtasks it had before it was completed. Returns false
+    /// if the task wasn't closed.
+    pub fn reopen(&mut self) -> bool {
+        if !self.status.is_closed() {
+            return false;
+        }
+        if let Some(state) = self.reopen.take() {
+            fn restore(nodes: &mut [SubTask], completed: &[usize]) {
+                for node in nodes {
+                    node.completed = completed.contains(&node.id);
+                    restore(&mut node.subtasks, completed);
+                }
+            }
+            restore(&mut self.subtasks, &state.completed_subtasks);
+            self.progress = state.progress;
+            self.roll_up_subtasks();
+        }
+        self.completed_at = None;
+        self.status = self.open_status();
+        true
+    }
+
+    /// Rolls subtask changes up into the task and keeps its status in line:
+    /// unchecking a subtask (or adding a new one) reopens a done task.
+    /// Returns true when the change checked off the last open subtask of an
+    /// open task, which the caller then completes with
+    /// `commands::settle_subtasks_in` so a recurring task moves on too.
+    pub fn settle_subtasks(&mut self) -> bool {
+        if self.subtasks.is_empty() {
+            return false;
+        }
+        self.roll_up_subtasks();
+
+        match self.status {
+            Status::Cancelled => {}
+            Status::Done if self.progress < 100 => {
+                self.reopen = None;
+                self.completed_at = None;
+                self.status = self.open_status();
+            }
+            Status::Done => {}
+            _ if self.progress == 100 => return true,
+            Status::Pending if self.progress > 0 => self.status = Status::InProgress,
+            _ => {}
+        }
+        false
+    }
+
+    /// The status an unfinished task goes back to.
+    fn open_status(&self) -> Status {
+        if self.progress > 0 {
+            Status::InProgress
+        } else {
+            Status::Pending
+        }
+    }
+}
+
+/// The current local time in the format tasks store it.
+pub fn timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// When a recurring task comes due again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecurrenceRule {
+    Daily,
+    /// On each of these weekdays.
+    Weekly(Vec<Weekday>),
+    /// On this day of every month, or the month's last day if it is shorter.
+    Monthly(u32),
+    /// This many days after the previous occurrence was completed.
+    AfterCompletion(u32),
+}
+
+impl RecurrenceRule {
+    /// The due date of the occurrence after one due on `due` and completed
+    /// on `completed`. Calendar rules never schedule into the past: a late
+    /// completion skips ahead to the first matching date from today on.
+    pub fn next_due(&self, due: Option<NaiveDate>, completed: NaiveDate) -> NaiveDate {
+        let today = Local::now().date_naive();
+        if let RecurrenceRule::AfterCompletion(days) = self {
+            return completed + Days::new(u64::from(*days));
+        }
+
+        let mut next = self.following(due.unwrap_or(completed));
+        while next < today {
+            next = self.following(next);
+        }
+        next
+    }
+
+    /// The first date matching the rule on or after `from`.
+    pub fn first_due(&self, from: NaiveDate) -> NaiveDate {
+        match self {
+            RecurrenceRule::Daily | RecurrenceRule::AfterCompletion(_) => from,
+            _ => self.following(from.pred_opt().unwrap_or(from)),
+        }
+    }
+
+    /// The first date matching the rule strictly after `date`.
+    fn following(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            RecurrenceRule::Daily => date + Days::new(1),
+            RecurrenceRule::AfterCompletion(days) => date + Days::new(u64::from(*days)),
+            RecurrenceRule::Weekly(days) => (1..=7)
+                .map(|n| date + Days::new(n))
+                .find(|d| days.contains(&d.weekday()))
+                .unwrap_or(date + Days::new(7)),
+            RecurrenceRule::Monthly(day) => {
+                let this_month = day_in_month(date.year(), date.month(), *day);
+                if this_month > date {
+                    this_month
+                } else if date.month() == 12 {
+                    day_in_month(date.year() + 1, 1, *day)
+                } else {
+                    day_in_month(date.year(), date.month() + 1, *day)
+                }
+            }
+        }
+    }
+}
+
+/// `day` of the given month, clamped to the month's last day.
+fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day.min(31))
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap_or_default()
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecurrenceRule::Daily => write!(f, "daily"),
+            RecurrenceRule::Weekly(days) => {
+                let days: Vec<String> = days.iter().map(|d| d.to_string().to_lowercase()).collect();
+                write!(f, "weekly:{}", days.join(","))
+            }
+            RecurrenceRule::Monthly(day) => write!(f, "monthly:{}", day),
+            RecurrenceRule::AfterCompletion(days) => write!(f, "every:{}d", days),
+        }
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    /// Parses `daily`, `weekly:mon,thu`, `monthly:15` or `every:3d`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_lowercase();
+        let (kind, arg) = match normalized.split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (normalized.as_str(), None),
+        };
+
+        match (kind, arg) {
+            ("daily", None) => Ok(RecurrenceRule::Daily),
+            ("weekly", Some(days)) => {
+                let mut weekdays = Vec::new();
+                for day in days.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+                    let weekday: Weekday = day
+                        .parse()
+                        .map_err(|_| format!("unknown weekday \"{}\"", day))?;
+                    if !weekdays.contains(&weekday) {
+                        weekdays.push(weekday);
+                    }
+                }
+                if weekdays.is_empty() {
+                    return Err("weekly needs at least one weekday, e.g. weekly:mon,thu".to_string());
+                }
+                weekdays.sort_by_key(|d| d.num_days_from_monday());
+                Ok(RecurrenceRule::Weekly(weekdays))
+            }
+            ("monthly", Some(day)) => match day.parse::<u32>() {
+                Ok(day @ 1..=31) => Ok(RecurrenceRule::Monthly(day)),
+                _ => Err(format!("monthly needs a day between 1 and 31, not \"{}\"", day)),
+            },
+            ("every", Some(days)) => match days.trim_end_matches('d').parse::<u32>() {
+                Ok(days) if days > 0 => Ok(RecurrenceRule::AfterCompletion(days)),
+                _ => Err(format!("every needs a number of days, e.g. every:3d, not \"{}\"", days)),
+            },
+            _ => Err(format!(
+                "unknown recurrence \"{}\" (expected daily, weekly:mon,thu, monthly:15 or every:3d)",
+                s
+            )),
+        }
+    }
+}
+
+pub fn parse_recurrence(s: &str) -> Result<RecurrenceRule, String> {
+    s.parse()
+}
+
+impl Serialize for RecurrenceRule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RecurrenceRule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The template a recurring task is created from. Completing an occurrence
+/// spawns the next one from here, so editing the series changes every
+/// occurrence still to come.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Series {
+    pub uuid: String,
+    pub rule: RecurrenceRule,
+    pub name: String,
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The subtask tree each occurrence starts with, all unchecked.
+    #[serde(default, deserialize_with = "deserialize_subtask_templates")]
+    pub subtasks: Vec<SubTask>,
+}
+
+/// Series written before subtasks were trees kept only subtask names.
+fn deserialize_subtask_templates<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<SubTask>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Template {
+        Name(String),
+        Node(SubTask),
+    }
+
+    Ok(Vec::<Template>::deserialize(deserializer)?
+        .into_iter()
+        .map(|t| match t {
+            Template::Name(name) => SubTask::new(name),
+            Template::Node(node) => node,
+        })
+        .collect())
+}
+
+impl Series {
+    /// Takes the template fields from an occurrence.
+    pub fn from_task(task: &Task, rule: RecurrenceRule) -> Self {
+        let mut series = Self {
+            uuid: new_uuid(),
+            rule,
+            name: String::new(),
+            priority: task.priority.clone(),
+            tags: Vec::new(),
+            project: None,
+            description: None,
+            subtasks: Vec::new(),
+        };
+        series.update_from(task);
+        series
+    }
+
+    pub fn update_from(&mut self, task: &Task) {
+        self.name = task.name.clone();
+        self.priority = task.priority.clone();
+        self.tags = task.tags.clone();
+        self.project = task.project.clone();
+        self.description = task.description.clone();
+        self.subtasks = task.subtasks.clone();
+        for subtask in &mut self.subtasks {
+            subtask.set_completed(false);
+        }
+    }
+
+    /// A fresh, pending occurrence due on `due`. The caller assigns the ID.
+    pub fn occurrence(&self, due: NaiveDate) -> Task {
+        Task {
+            id: 0,
+            uuid: Some(new_uuid()),
+            name: self.name.clone(),
+            priority: self.priority.clone(),
+            status: Status::Pending,
+            progress: 0,
+            due_date: Some(due.format("%Y-%m-%d").to_string()),
+            tags: self.tags.clone(),
+            project: self.project.clone(),
+            created_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            completed_at: None,
+            subtasks: self.subtasks.clone(),
+            description: self.description.clone(),
+            annotations: Vec::new(),
+            series: Some(self.uuid.clone()),
+            depends_on: Vec::new(),
+            parent: None,
+            reopen: None,
+        }
+    }
+}
+
+pub fn new_uuid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Where tasks are persisted. `Sqlite` needs a build with the `sqlite`
+/// cargo feature.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Sqlite,
+    /// An append-only event log, replayed on load.
+    Events,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
     pub tasks_dir: String,
     pub user_name: String,
     pub default_priority: String,
+    #[serde(default = "default_priority_levels")]
+    pub priorities: Vec<String>,
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// How many of the most recent saves to keep a backup of.
+    #[serde(default = "default_backups")]
+    pub backups: usize,
+    /// How many days back to keep one backup per day.
+    #[serde(default = "default_daily_backups")]
+    pub daily_backups: usize,
+    /// Move tasks closed this many days ago to the archive automatically.
+    #[serde(default)]
+    pub auto_archive_days: Option<u32>,
+    /// The list commands use unless `-L` names another.
+    #[serde(default = "default_list")]
+    pub current_list: String,
+}
+
+fn default_list() -> String {
+    DEFAULT_LIST.to_string()
+}
+
+fn default_backups() -> usize {
+    10
+}
+
+fn default_daily_backups() -> usize {
+    7
 }
 
 impl Default for AppConfig {
@@ -71,6 +955,12 @@ impl Default for AppConfig {
             tasks_dir: get_default_tasks_dir(),
             user_name: "User".to_string(),
             default_priority: "medium".to_string(),
+            priorities: default_priority_levels(),
+            backend: StorageBackend::default(),
+            backups: default_backups(),
+            daily_backups: default_daily_backups(),
+            auto_archive_days: None,
+            current_list: default_list(),
         }
     }
 }
@@ -97,8 +987,400 @@ pub fn is_config_exists() -> bool {
         .unwrap_or(false)
 }
 
+/// The list whose store lives directly in the tasks root.
+pub const DEFAULT_LIST: &str = "default";
+
+static LIST_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Makes `name` the active list for the rest of this run, ahead of
+/// `AppConfig::current_list` (`rtask -L <name> ...`).
+pub fn set_list_override(name: String) {
+    let _ = LIST_OVERRIDE.set(name);
+}
+
+static GLOBAL_ONLY: AtomicBool = AtomicBool::new(false);
+static LOCAL_STORE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// The name of the directory that holds a store of its own, e.g. one per
+/// repository.
+pub const LOCAL_STORE_DIR: &str = ".rtask";
+
+/// Ignores any local `.rtask/` store for the rest of this run
+/// (`rtask --global ...`).
+pub fn set_global_only() {
+    GLOBAL_ONLY.store(true, Ordering::Relaxed);
+}
+
+/// The nearest `.rtask/` directory in the current directory or one of its
+/// parents, the way git finds `.git`.
+pub fn find_local_store() -> Option<PathBuf> {
+    local_store_above(&std::env::current_dir().ok()?)
+}
+
+/// The nearest `.rtask/` directory in `dir` or one of its parents.
+fn local_store_above(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(LOCAL_STORE_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+/// The local store in use, if any: [`find_local_store`], looked up once
+/// per run, unless `--global` was given.
+pub fn local_store() -> Option<PathBuf> {
+    if GLOBAL_ONLY.load(Ordering::Relaxed) {
+        return None;
+    }
+    LOCAL_STORE.get_or_init(find_local_store).clone()
+}
+
+/// Where every list is kept: the local `.rtask/` store if there is one,
+/// otherwise `AppConfig::tasks_dir`.
+pub fn get_tasks_root() -> PathBuf {
+    match local_store() {
+        Some(dir) => dir,
+        None => PathBuf::from(load_config().tasks_dir),
+    }
+}
+
+/// The list commands read and change: the one given with `-L`, or else
+/// the one picked with `rtask use`. A local store always starts out on
+/// its default list.
+pub fn active_list() -> String {
+    match LIST_OVERRIDE.get() {
+        Some(name) => name.clone(),
+        None if local_store().is_some() => DEFAULT_LIST.to_string(),
+        None => load_config().current_list,
+    }
+}
+
+/// Where the store of list `name` lives: the [tasks root](get_tasks_root)
+/// itself for the default list, `<root>/lists/<name>` for the others.
+pub fn get_list_dir(name: &str) -> PathBuf {
+    let root = get_tasks_root();
+    if name == DEFAULT_LIST {
+        root
+    } else {
+        root.join("lists").join(name)
+    }
+}
+
+/// Every list, the default one first and the rest by name.
+pub fn list_names() -> Vec<String> {
+    list_names_in(&get_tasks_root())
+}
+
+fn list_names_in(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root.join("lists"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .filter(|name| name != DEFAULT_LIST)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_LIST.to_string());
+    names
+}
+
+/// List names become directory names, so they are kept to letters,
+/// digits, `-` and `_`.
+pub fn parse_list_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "Invalid list name '{}'. Use letters, digits, '-' and '_'",
+            name
+        ))
+    }
+}
+
+/// The directory holding the active list's store and everything kept
+/// alongside it.
+pub fn get_store_dir() -> PathBuf {
+    get_list_dir(&active_list())
+}
+
 pub fn get_tasks_file() -> PathBuf {
-    let config = load_config();
-    let path = Path::new(&config.tasks_dir).join("tasks.json");
-    path
-}
\ No newline at end of file
+    get_store_dir().join("tasks.json")
+}
+
+pub fn get_tasks_db_file() -> PathBuf {
+    get_store_dir().join("tasks.db")
+}
+
+pub fn get_journal_file() -> PathBuf {
+    get_store_dir().join("journal.json")
+}
+
+pub fn get_events_file() -> PathBuf {
+    get_store_dir().join("events.jsonl")
+}
+
+/// History of every change for the backends other than the event log,
+/// which keeps its own in `events.jsonl`.
+pub fn get_history_file() -> PathBuf {
+    get_store_dir().join("history.jsonl")
+}
+
+pub fn get_backups_dir() -> PathBuf {
+    get_store_dir().join("backups")
+}
+
+pub fn get_archive_dir() -> PathBuf {
+    get_store_dir().join("archive")
+}
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A pending task with only an ID and a name, for tests across the crate.
+    pub(crate) fn task(id: usize, name: &str) -> Task {
+        Task {
+            id,
+            uuid: Some(new_uuid()),
+            name: name.to_string(),
+            priority: Priority::from("medium".to_string()),
+            status: Status::Pending,
+            progress: 0,
+            due_date: None,
+            tags: Vec::new(),
+            project: None,
+            created_at: "2100-01-01 09:00".to_string(),
+            completed_at: None,
+            subtasks: Vec::new(),
+            description: None,
+            annotations: Vec::new(),
+            series: None,
+            depends_on: Vec::new(),
+            parent: None,
+            reopen: None,
+        }
+    }
+
+    /// A new, empty directory for a test's files.
+    pub(crate) fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rtask-test-{}", new_uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn daily_follows_the_next_day() {
+        assert_eq!(RecurrenceRule::Daily.following(date("2100-01-31")), date("2100-02-01"));
+    }
+
+    #[test]
+    fn weekly_picks_the_next_listed_weekday() {
+        let rule: RecurrenceRule = "weekly:thu,mon".parse().unwrap();
+        assert_eq!(rule, RecurrenceRule::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+        // 2100-01-04 is a Monday.
+        assert_eq!(rule.following(date("2100-01-04")), date("2100-01-07"));
+        assert_eq!(rule.following(date("2100-01-07")), date("2100-01-11"));
+    }
+
+    #[test]
+    fn monthly_clamps_to_short_months_and_wraps_the_year() {
+        let rule = RecurrenceRule::Monthly(31);
+        assert_eq!(rule.following(date("2100-01-31")), date("2100-02-28"));
+        assert_eq!(rule.following(date("2100-02-28")), date("2100-03-31"));
+        assert_eq!(rule.following(date("2100-12-31")), date("2101-01-31"));
+        assert_eq!(RecurrenceRule::Monthly(15).following(date("2100-01-10")), date("2100-01-15"));
+    }
+
+    #[test]
+    fn next_due_counts_from_the_due_date() {
+        let rule = RecurrenceRule::Monthly(15);
+        assert_eq!(rule.next_due(Some(date("2100-01-15")), date("2099-12-01")), date("2100-02-15"));
+    }
+
+    #[test]
+    fn next_due_never_lands_in_the_past() {
+        let today = Local::now().date_naive();
+        let next = RecurrenceRule::Daily.next_due(Some(date("2000-01-01")), today);
+        assert_eq!(next, today);
+    }
+
+    #[test]
+    fn after_completion_counts_from_the_completion_date() {
+        let rule = RecurrenceRule::AfterCompletion(3);
+        assert_eq!(rule.next_due(Some(date("2000-01-01")), date("2100-01-30")), date("2100-02-02"));
+    }
+
+    #[test]
+    fn recurrence_rules_round_trip_through_text() {
+        for text in ["daily", "weekly:mon,fri", "monthly:15", "every:3d"] {
+            let rule: RecurrenceRule = text.parse().unwrap();
+            assert_eq!(rule.to_string(), text);
+        }
+        assert!("monthly:32".parse::<RecurrenceRule>().is_err());
+        assert!("weekly:".parse::<RecurrenceRule>().is_err());
+    }
+
+    #[test]
+    fn statuses_read_legacy_spellings_and_write_kebab_case() {
+        for (text, status) in [
+            ("pending", Status::Pending),
+            ("In Progress", Status::InProgress),
+            ("in_progress", Status::InProgress),
+            ("started", Status::InProgress),
+            ("completed", Status::Done),
+            ("canceled", Status::Cancelled),
+        ] {
+            assert_eq!(text.parse::<Status>(), Ok(status), "{}", text);
+        }
+        assert!("finished-ish".parse::<Status>().is_err());
+
+        let status: Status = serde_json::from_str("\"done\"").unwrap();
+        assert_eq!(status, Status::Done);
+        assert_eq!(serde_json::to_string(&Status::InProgress).unwrap(), "\"in-progress\"");
+        let closed: Vec<Status> = Status::ALL.into_iter().filter(Status::is_closed).collect();
+        assert_eq!(closed, vec![Status::Done, Status::Cancelled]);
+    }
+
+    #[test]
+    fn priorities_follow_the_configured_scale() {
+        let scale = PriorityScale::new(["P0", "P1", "P2", "P3", "P4"].map(String::from).to_vec());
+        assert_eq!(scale.parse("p1").unwrap().as_str(), "P1");
+        assert!(scale.parse("high").is_err());
+        assert_eq!(scale.rank(&Priority::from("P3".to_string())), Some(3));
+        assert_eq!(scale.rank(&Priority::from("urgent".to_string())), None);
+
+        let filter = PriorityMatch { priority: scale.parse("P2").unwrap(), or_higher: true };
+        let levels: Vec<String> = filter.levels(&scale).iter().map(|p| p.to_string()).collect();
+        assert_eq!(levels, vec!["P0", "P1", "P2"]);
+
+        let config = AppConfig { default_priority: "medium".to_string(), ..AppConfig::default() };
+        assert_eq!(scale.default_priority(&config).as_str(), "P2");
+        assert_eq!(PriorityScale::new(vec![" ".to_string()]).levels(), default_priority_levels());
+    }
+
+    #[test]
+    fn reopening_restores_the_progress_from_before_completion() {
+        let mut task = task(1, "Ship");
+        task.subtasks = vec![SubTask::new("Build"), SubTask::new("Test")];
+        task.assign_subtask_ids();
+        task.subtasks[0].completed = true;
+        task.settle_subtasks();
+        assert_eq!((task.status, task.progress), (Status::InProgress, 50));
+
+        task.complete();
+        assert_eq!((task.status, task.progress), (Status::Done, 100));
+        assert!(task.completed_at.is_some());
+
+        assert!(task.reopen());
+        assert_eq!((task.status, task.progress, task.completed_at.clone()), (Status::InProgress, 50, None));
+        assert!(task.subtasks[0].completed && !task.subtasks[1].completed);
+        assert!(!task.reopen());
+    }
+
+    #[test]
+    fn unchecking_a_subtask_reopens_a_done_task() {
+        let mut task = task(1, "Ship");
+        task.subtasks = vec![SubTask::new("Build"), SubTask::new("Test")];
+        task.assign_subtask_ids();
+        task.complete();
+
+        task.subtasks[1].completed = false;
+        assert!(!task.settle_subtasks());
+        assert_eq!((task.status, task.progress, task.completed_at.clone()), (Status::InProgress, 50, None));
+
+        // Checking it again leaves completing the task to the caller.
+        task.subtasks[1].completed = true;
+        assert!(task.settle_subtasks());
+        assert_eq!(task.progress, 100);
+    }
+
+    #[test]
+    fn lists_are_directories_with_safe_names() {
+        assert_eq!(parse_list_name("client-a_2").unwrap(), "client-a_2");
+        for bad in ["", "../up", "a b", "work/api"] {
+            assert!(parse_list_name(bad).is_err(), "{}", bad);
+        }
+
+        let root = temp_dir();
+        assert_eq!(list_names_in(&root), vec![DEFAULT_LIST]);
+        for name in ["work", "personal", DEFAULT_LIST] {
+            fs::create_dir_all(root.join("lists").join(name)).unwrap();
+        }
+        fs::write(root.join("lists").join("notes.txt"), "").unwrap();
+        assert_eq!(list_names_in(&root), vec![DEFAULT_LIST, "personal", "work"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn the_nearest_local_store_wins() {
+        let root = temp_dir();
+        let repo = root.join("repo");
+        let nested = repo.join("app").join(LOCAL_STORE_DIR);
+        let deep = repo.join("app").join("src").join("bin");
+        fs::create_dir_all(&deep).unwrap();
+        assert_eq!(local_store_above(&deep), None);
+
+        fs::create_dir_all(repo.join(LOCAL_STORE_DIR)).unwrap();
+        assert_eq!(local_store_above(&deep), Some(repo.join(LOCAL_STORE_DIR)));
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(local_store_above(&deep), Some(nested));
+        // A file of that name isn't a store.
+        fs::write(deep.join(LOCAL_STORE_DIR), "").unwrap();
+        assert_eq!(local_store_above(&deep), Some(repo.join("app").join(LOCAL_STORE_DIR)));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn ids_are_never_given_out_twice() {
+        let mut data = TaskData::from_legacy(vec![task(3, "three"), task(5, "five")]);
+        assert_eq!(data.allocate_id(), 6);
+        data.remove(5);
+        data.remove(6);
+        assert_eq!(data.allocate_id(), 7);
+        assert_eq!(data.find(3).map(|t| t.name.as_str()), Some("three"));
+
+        // A counter behind the IDs in use (a hand edit) is moved past them.
+        data.next_id = 2;
+        data.normalize();
+        assert_eq!(data.allocate_id(), 4);
+        assert!(data.tasks.iter().all(|t| t.uuid.is_some()));
+    }
+
+    #[test]
+    fn normalize_keeps_links_to_tasks_that_left_the_store() {
+        let mut archived = task(1, "archived");
+        archived.status = Status::Done;
+        let mut waiting = task(2, "waiting");
+        waiting.depends_on = vec![1, 2, 9];
+        waiting.parent = Some(1);
+        let mut data = TaskData {
+            next_id: 3,
+            tasks: vec![waiting],
+            series: Vec::new(),
+        };
+
+        data.normalize();
+        // Task 1 was given out and may be in the archive; 9 never existed.
+        assert_eq!(data.tasks[0].depends_on, vec![1]);
+        assert_eq!(data.tasks[0].parent, Some(1));
+    }
+
+    #[test]
+    fn remove_drops_links_to_the_removed_task() {
+        let mut waiting = task(2, "waiting");
+        waiting.depends_on = vec![1];
+        waiting.parent = Some(1);
+        let mut data = TaskData::from_legacy(vec![task(1, "first"), waiting]);
+
+        assert_eq!(data.remove(1).map(|t| t.name), Some("first".to_string()));
+        assert!(data.tasks[0].depends_on.is_empty());
+        assert_eq!(data.tasks[0].parent, None);
+        assert!(data.remove(1).is_none());
+    }
+}
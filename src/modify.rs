@@ -0,0 +1,362 @@
+use colored::*;
+use dialoguer::{Input, Select};
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit, validate_due_date};
+use crate::model::{parse_project, Priority, PriorityScale, Task, TaskData};
+use crate::store::StoreError;
+
+/// Field changes for an existing task. `None` leaves a field alone;
+/// `due: Some(None)` clears the due date.
+#[derive(Debug, Clone, Default)]
+pub struct TaskChanges {
+    pub name: Option<String>,
+    pub priority: Option<Priority>,
+    pub due: Option<Option<String>>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    /// `Some(None)` takes the task out of its project.
+    pub project: Option<Option<String>>,
+    /// `Some(None)` clears the description.
+    pub description: Option<Option<String>>,
+}
+
+impl TaskChanges {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.priority.is_none()
+            && self.due.is_none()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+            && self.project.is_none()
+            && self.description.is_none()
+    }
+
+    /// Splits `+tag` / `-tag` arguments into tags to add and remove. A tag
+    /// without a sign is added.
+    pub fn parse_tags(&mut self, args: &[String]) {
+        for arg in args {
+            if let Some(tag) = arg.strip_prefix('-') {
+                self.remove_tags.push(tag.trim().to_string());
+            } else {
+                let tag = arg.strip_prefix('+').unwrap_or(arg);
+                self.add_tags.push(tag.trim().to_string());
+            }
+        }
+        self.add_tags.retain(|t| !t.is_empty());
+        self.remove_tags.retain(|t| !t.is_empty());
+    }
+
+    /// Applies the changes and returns the names of the fields that
+    /// actually changed.
+    pub fn apply(&self, task: &mut Task) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+
+        if let Some(name) = &self.name {
+            if *name != task.name {
+                task.name = name.clone();
+                changed.push("name");
+            }
+        }
+
+        if let Some(priority) = &self.priority {
+            if *priority != task.priority {
+                task.priority = priority.clone();
+                changed.push("priority");
+            }
+        }
+
+        if let Some(due) = &self.due {
+            if *due != task.due_date {
+                task.due_date = due.clone();
+                changed.push("due date");
+            }
+        }
+
+        let tags_before = task.tags.clone();
+        task.tags.retain(|tag| !self.remove_tags.contains(tag));
+        for tag in &self.add_tags {
+            if !task.tags.contains(tag) {
+                task.tags.push(tag.clone());
+            }
+        }
+        if task.tags != tags_before {
+            changed.push("tags");
+        }
+
+        if let Some(project) = &self.project {
+            if *project != task.project {
+                task.project = project.clone();
+                changed.push("project");
+            }
+        }
+
+        if let Some(description) = &self.description {
+            if *description != task.description {
+                task.description = description.clone();
+                changed.push("description");
+            }
+        }
+
+        changed
+    }
+}
+
+/// Checks the new name and due date, normalizing the date. Prints the
+/// problem and returns false if something is wrong.
+fn validate_changes(changes: &mut TaskChanges) -> bool {
+    if changes.is_empty() {
+        println!("{}", "Nothing to change".yellow());
+        return false;
+    }
+
+    if let Some(name) = &changes.name {
+        if name.trim().is_empty() {
+            println!("{}", "Task name can't be empty".red().bold());
+            return false;
+        }
+    }
+
+    if let Some(Some(due)) = &mut changes.due {
+        match validate_due_date(due) {
+            Ok(valid) => *due = valid,
+            Err(e) => {
+                println!("{}", e.red().bold());
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Applies `changes` to task `id` inside a transaction, returning its name
+/// and the fields that changed.
+fn modify_in(
+    data: &mut TaskData,
+    id: usize,
+    changes: &TaskChanges,
+    series: bool,
+) -> Result<(String, Vec<&'static str>), StoreError> {
+    let task = data.find(id).ok_or(StoreError::NotFound(id))?;
+    let series = match (series, task.series.clone()) {
+        (false, _) => None,
+        (true, Some(uuid)) if data.find_series(&uuid).is_some() => Some(uuid),
+        (true, _) => {
+            return Err(StoreError::Rejected(format!(
+                "Task {} is not part of a recurring series",
+                id
+            )))
+        }
+    };
+
+    let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    let name = task.name.clone();
+    let mut changed = changes.apply(task);
+
+    if let Some(uuid) = series {
+        let task = task.clone();
+        let template = data.find_series_mut(&uuid).ok_or(StoreError::NotFound(id))?;
+        let before = template.clone();
+        template.update_from(&task);
+        if *template != before {
+            changed.push("series");
+        }
+    }
+    Ok((name, changed))
+}
+
+/// Applies `changes` to task `id`. With `series`, the changes also go to
+/// the recurring series the task belongs to, so later occurrences get them.
+pub fn modify_task(id: usize, changes: TaskChanges, series: bool) {
+    modify_tasks(vec![id], changes, series);
+}
+
+/// Applies `changes` to every task in `ids` as one change, so a single
+/// `rtask undo` reverts all of them.
+pub fn modify_tasks(ids: Vec<usize>, mut changes: TaskChanges, series: bool) {
+    if !validate_changes(&mut changes) {
+        return;
+    }
+
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        ids.iter()
+            .map(|id| modify_in(data, *id, &changes, series))
+            .collect::<Result<Vec<_>, _>>()
+    });
+
+    match result {
+        Ok(results) => {
+            let total = results.len();
+            let mut modified = 0;
+            for (name, changed) in results {
+                if changed.is_empty() {
+                    if total == 1 {
+                        println!("{}", "Task already matches; nothing changed".yellow());
+                    }
+                    continue;
+                }
+                modified += 1;
+                println!(
+                    "{} {} {}",
+                    "✓ Modified task:".green().bold(),
+                    name.bright_white(),
+                    format!("({})", changed.join(", ")).cyan()
+                );
+            }
+            if total > 1 {
+                let summary = if modified == total {
+                    format!("Modified {} tasks", total)
+                } else {
+                    format!("Modified {} of {} tasks; the rest already matched", modified, total)
+                };
+                println!("{}", summary.cyan().bold());
+            }
+        }
+        Err(e) => print_store_error("Error modifying task:", &e),
+    }
+}
+
+pub fn interactive_modify_task() {
+    let tasks = load_or_exit().tasks;
+
+    if tasks.is_empty() {
+        println!("{}", "No tasks found".yellow());
+        return;
+    }
+
+    let task_names: Vec<String> = tasks
+        .iter()
+        .map(|t| format!("[{}] {} ({})", t.id, t.name, t.status))
+        .collect();
+
+    let selection = Select::new()
+        .with_prompt("Select task to modify")
+        .items(&task_names)
+        .interact();
+
+    let task = match selection {
+        Ok(idx) => &tasks[idx],
+        Err(_) => return,
+    };
+
+    let name: String = Input::new()
+        .with_prompt("Task name")
+        .with_initial_text(&task.name)
+        .interact_text()
+        .unwrap();
+
+    let scale = PriorityScale::load();
+    let mut priority_items = scale.levels().to_vec();
+    let current_priority = match scale.rank(&task.priority) {
+        Some(rank) => rank,
+        None => {
+            // Keep a priority from an older scale selectable as-is
+            priority_items.push(task.priority.to_string());
+            priority_items.len() - 1
+        }
+    };
+
+    let priority_idx = Select::new()
+        .with_prompt("Priority")
+        .items(&priority_items)
+        .default(current_priority)
+        .interact()
+        .unwrap();
+
+    let due: String = Input::new()
+        .with_prompt("Due date (YYYY-MM-DD, empty for none)")
+        .with_initial_text(task.due_date.clone().unwrap_or_default())
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            if input.trim().is_empty() {
+                Ok(())
+            } else {
+                validate_due_date(input).map(|_| ())
+            }
+        })
+        .interact_text()
+        .unwrap();
+
+    let tags_input: String = Input::new()
+        .with_prompt("Tags (comma separated)")
+        .with_initial_text(task.tags.join(", "))
+        .allow_empty(true)
+        .interact_text()
+        .unwrap();
+
+    let tags: Vec<String> = tags_input
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let project: String = Input::new()
+        .with_prompt("Project (e.g. acme.backend, empty for none)")
+        .with_initial_text(task.project.clone().unwrap_or_default())
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            if input.trim().is_empty() {
+                Ok(())
+            } else {
+                parse_project(input).map(|_| ())
+            }
+        })
+        .interact_text()
+        .unwrap();
+
+    let changes = TaskChanges {
+        name: Some(name),
+        priority: Some(Priority::from(priority_items[priority_idx].clone())),
+        due: Some(if due.trim().is_empty() { None } else { Some(due.trim().to_string()) }),
+        add_tags: tags.iter().filter(|t| !task.tags.contains(t)).cloned().collect(),
+        remove_tags: task.tags.iter().filter(|t| !tags.contains(t)).cloned().collect(),
+        project: Some(parse_project(&project).ok()),
+        description: None,
+    };
+
+    modify_task(task.id, changes, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+    use crate::model::{RecurrenceRule, Series};
+
+    #[test]
+    fn applies_only_the_fields_that_change() {
+        let mut task = task(1, "Draft");
+        task.tags = vec!["work".to_string(), "later".to_string()];
+        task.due_date = Some("2100-01-01".to_string());
+
+        let mut changes = TaskChanges {
+            name: Some("Draft".to_string()),
+            due: Some(None),
+            ..TaskChanges::default()
+        };
+        changes.parse_tags(&["+urgent".to_string(), "-later".to_string(), "work".to_string()]);
+
+        assert_eq!(changes.apply(&mut task), vec!["due date", "tags"]);
+        assert_eq!(task.due_date, None);
+        assert_eq!(task.tags, vec!["work", "urgent"]);
+        assert!(changes.apply(&mut task).is_empty());
+    }
+
+    #[test]
+    fn series_changes_need_a_recurring_task() {
+        let mut data = TaskData::from_legacy(vec![task(1, "Standup")]);
+        let changes = TaskChanges { name: Some("Daily standup".to_string()), ..TaskChanges::default() };
+
+        assert!(matches!(modify_in(&mut data, 1, &changes, true), Err(StoreError::Rejected(_))));
+        assert!(matches!(modify_in(&mut data, 9, &changes, false), Err(StoreError::NotFound(9))));
+        assert_eq!(data.tasks[0].name, "Standup");
+
+        let series = Series::from_task(&data.tasks[0], RecurrenceRule::Daily);
+        data.tasks[0].series = Some(series.uuid.clone());
+        data.series.push(series);
+        let (name, changed) = modify_in(&mut data, 1, &changes, true).unwrap();
+        assert_eq!((name.as_str(), changed), ("Standup", vec!["name", "series"]));
+        assert_eq!(data.series[0].name, "Daily standup");
+    }
+}
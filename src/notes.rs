@@ -0,0 +1,68 @@
+use chrono::Local;
+use colored::*;
+use dialoguer::{Input, Select};
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit};
+use crate::model::Annotation;
+use crate::store::StoreError;
+
+/// Appends a timestamped annotation to a task. Annotations are never
+/// rewritten; add another one to correct an earlier note.
+pub fn annotate_task(id: usize, text: String) {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        println!("{}", "Annotation can't be empty".red().bold());
+        return;
+    }
+
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        task.annotations.push(Annotation {
+            created_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            text: text.clone(),
+        });
+        Ok(task.name.clone())
+    });
+
+    match result {
+        Ok(name) => {
+            println!(
+                "{} {} {}",
+                "✓ Annotated task:".green().bold(),
+                name.bright_white(),
+                format!("({})", text).cyan()
+            );
+        }
+        Err(e) => print_store_error("Error annotating task:", &e),
+    }
+}
+
+pub fn interactive_annotate_task() {
+    let tasks = load_or_exit().tasks;
+
+    if tasks.is_empty() {
+        println!("{}", "No tasks found".yellow());
+        return;
+    }
+
+    let task_names: Vec<String> = tasks
+        .iter()
+        .map(|t| format!("[{}] {} ({})", t.id, t.name, t.status))
+        .collect();
+
+    let selection = Select::new()
+        .with_prompt("Select task to annotate")
+        .items(&task_names)
+        .interact();
+
+    if let Ok(idx) = selection {
+        let text: String = Input::new()
+            .with_prompt("Annotation")
+            .interact_text()
+            .unwrap();
+
+        annotate_task(tasks[idx].id, text);
+    }
+}
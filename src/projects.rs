@@ -0,0 +1,83 @@
+use colored::*;
+use std::collections::BTreeMap;
+
+use crate::commands::{format_progress_bar, load_or_exit};
+use crate::model::{Status, Task};
+
+/// Task counts for one node of the project tree, including every project
+/// below it.
+#[derive(Debug, Clone, Default)]
+struct ProjectStats {
+    pending: usize,
+    done: usize,
+    progress: usize,
+}
+
+impl ProjectStats {
+    fn add(&mut self, task: &Task) {
+        if task.status == Status::Done {
+            self.done += 1;
+        } else {
+            self.pending += 1;
+        }
+        self.progress += task.progress as usize;
+    }
+
+    fn average_progress(&self) -> u8 {
+        self.progress
+            .checked_div(self.pending + self.done)
+            .unwrap_or(0) as u8
+    }
+}
+
+/// Prints every project as a tree with its open and done task counts and
+/// average progress. Each node counts the tasks of its sub-projects too;
+/// cancelled tasks are left out.
+pub fn show_projects() {
+    let data = load_or_exit();
+
+    // Keyed by path segments so `acme.backend` sorts right after `acme`.
+    let mut nodes: BTreeMap<Vec<&str>, ProjectStats> = BTreeMap::new();
+    let mut unassigned = ProjectStats::default();
+    for task in data.tasks.iter().filter(|t| t.status != Status::Cancelled) {
+        let Some(project) = &task.project else {
+            unassigned.add(task);
+            continue;
+        };
+        let parts: Vec<&str> = project.split('.').collect();
+        for depth in 1..=parts.len() {
+            nodes.entry(parts[..depth].to_vec()).or_default().add(task);
+        }
+    }
+
+    if nodes.is_empty() {
+        println!(
+            "{}",
+            "No projects yet. Give a task one with `rtask add ... --project acme.backend`".yellow()
+        );
+        return;
+    }
+
+    let label = |parts: &[&str]| format!("{}{}", "  ".repeat(parts.len() - 1), parts[parts.len() - 1]);
+    let width = nodes.keys().map(|parts| label(parts).chars().count()).max().unwrap_or(0).max(12);
+    let print_row = |name: String, stats: &ProjectStats| {
+        println!(
+            "{:<width$}  {:>4} pending  {:>4} done  {}",
+            name,
+            stats.pending,
+            stats.done,
+            format_progress_bar(stats.average_progress()),
+            width = width
+        );
+    };
+
+    println!("\n{}", "Projects".cyan().bold());
+    println!("{}", "-".repeat(8).cyan());
+    for (parts, stats) in &nodes {
+        print_row(label(parts), stats);
+    }
+    if unassigned.pending + unassigned.done > 0 {
+        print_row("(no project)".to_string(), &unassigned);
+    }
+    println!();
+}
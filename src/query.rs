@@ -0,0 +1,508 @@
+//! The filter language commands use to pick tasks, e.g.
+//! `status:pending priority:high+ due.before:friday +backend -blocked name~migr`.
+//!
+//! A query is a list of terms that must all hold. Each command-line word
+//! is one term, so `name~"db migration"` searches for the phrase even
+//! after the shell has dropped the quotes. Where a command takes the whole
+//! query as one word (the target of `modify`), terms are separated by
+//! spaces and double quotes keep spaces inside a value. Supported terms:
+//!
+//! - `12`, `id:3,5`: these task IDs
+//! - `status:pending,waiting`, `status:open`, `status:closed`
+//! - `priority:high`, `priority:medium+` (that level and more urgent)
+//! - `due:2025-06-01`, `due.before:friday`, `due.after:today`, `due:none`,
+//!   `due:any`; dates can also be `tomorrow`, a weekday, or `3d` / `2w`
+//!   from today
+//! - `project:acme` (and every project below it)
+//! - `+backend` / `tag:backend`: has the tag; `work*` matches a prefix
+//! - `+blocked`: waiting on open prerequisites or marked blocked;
+//!   `+overdue`: open and past its due date
+//! - `name~migr`, `description~text`: contains the text, ignoring case
+//! - any other word: the name, description or annotations contain it
+//!
+//! A leading `-` negates a term: `-backend`, `-tag:someday`,
+//! `-status:waiting`.
+
+use chrono::{Datelike, Days, Local, NaiveDate, Weekday};
+use colored::*;
+use dialoguer::Confirm;
+
+use crate::commands::{load_or_exit, print_task_line};
+use crate::deps::open_prerequisites;
+use crate::model::{in_project, parse_priority_match, parse_project, Priority, PriorityScale, Status, Task, TaskData};
+use crate::store::TagFilter;
+
+#[derive(Debug, Clone, PartialEq)]
+enum DueMatch {
+    On(NaiveDate),
+    Before(NaiveDate),
+    After(NaiveDate),
+    None,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextField {
+    Name,
+    Description,
+    /// Name, description and annotations.
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Ids(Vec<usize>),
+    Status(Vec<Status>),
+    Priority(Vec<Priority>),
+    Due(DueMatch),
+    Project(String),
+    /// Has a tag matching any of these patterns.
+    Tag(Vec<String>),
+    Text(TextField, String),
+    Blocked,
+    Overdue,
+    Not(Box<Term>),
+}
+
+/// A parsed filter expression. Every term must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parses the words of a query, as given on the command line, one term
+    /// per word. Double quotes left in a word are dropped.
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        Self::from_tokens(
+            words
+                .iter()
+                .map(|word| word.trim().replace('"', ""))
+                .filter(|word| !word.is_empty()),
+        )
+    }
+
+    /// Parses a whole query given as one string, with terms separated by
+    /// spaces outside double quotes.
+    pub fn parse_text(text: &str) -> Result<Self, String> {
+        Self::from_tokens(tokenize(text).into_iter())
+    }
+
+    fn from_tokens(tokens: impl Iterator<Item = String>) -> Result<Self, String> {
+        let today = Local::now().date_naive();
+        let mut query = Query::default();
+
+        for token in tokens {
+            let term = parse_term(&token, today)?;
+            // Bare IDs add up: `done 3 5` completes both.
+            if let (Term::Ids(ids), Some(Term::Ids(previous))) = (&term, query.terms.last_mut()) {
+                previous.extend(ids);
+                continue;
+            }
+            query.terms.push(term);
+        }
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The IDs, if the query is nothing but a list of task IDs.
+    pub fn ids(&self) -> Option<&[usize]> {
+        match self.terms.as_slice() {
+            [Term::Ids(ids)] => Some(ids),
+            _ => None,
+        }
+    }
+
+    /// Whether the query picks tasks by status or ID, in which case closed
+    /// tasks aren't hidden by default. A negated term (`-status:waiting`)
+    /// only narrows the selection, so it doesn't count.
+    pub fn selects_closed(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|term| matches!(term, Term::Ids(_) | Term::Status(_)))
+    }
+
+    /// Whether `task` matches. `data` is the whole store, for terms that
+    /// look at other tasks (`+blocked`).
+    pub fn matches(&self, task: &Task, data: &TaskData) -> bool {
+        let today = Local::now().date_naive();
+        self.terms.iter().all(|term| term_matches(term, task, data, today))
+    }
+
+    /// The tasks in `data` that match, in ID order. Closed tasks are left
+    /// out unless `include_closed` or the query asks for them by status
+    /// or ID.
+    pub fn select<'a>(&self, data: &'a TaskData, include_closed: bool) -> Vec<&'a Task> {
+        let include_closed = include_closed || self.selects_closed();
+        let mut tasks: Vec<&Task> = data
+            .tasks
+            .iter()
+            .filter(|t| include_closed || !t.status.is_closed())
+            .filter(|t| self.matches(t, data))
+            .collect();
+        tasks.sort_by_key(|t| t.id);
+        tasks
+    }
+}
+
+/// Splits a query string into terms at spaces outside double quotes,
+/// dropping the quotes.
+fn tokenize(word: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in word.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_term(token: &str, today: NaiveDate) -> Result<Term, String> {
+    if let Ok(id) = token.parse::<usize>() {
+        return Ok(Term::Ids(vec![id]));
+    }
+    if let Some(rest) = token.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+        let inner = if rest.contains(':') || rest.contains('~') {
+            parse_term(rest, today)?
+        } else {
+            tag_or_state(rest)
+        };
+        return Ok(Term::Not(Box::new(inner)));
+    }
+    if let Some(rest) = token.strip_prefix('+').filter(|rest| !rest.is_empty()) {
+        return Ok(tag_or_state(rest));
+    }
+
+    if let Some((field, text)) = token.split_once('~') {
+        let field = match field.to_lowercase().as_str() {
+            "name" => TextField::Name,
+            "description" | "desc" => TextField::Description,
+            "text" => TextField::Any,
+            other => return Err(format!("can't search `{}` with ~; use name~, description~ or text~", other)),
+        };
+        return Ok(Term::Text(field, text.to_lowercase()));
+    }
+
+    let Some((key, value)) = token.split_once(':') else {
+        return Ok(Term::Text(TextField::Any, token.to_lowercase()));
+    };
+    let values = || value.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+    match key.to_lowercase().as_str() {
+        "id" => values()
+            .map(|v| v.parse().map_err(|_| format!("`{}` is not a task ID", v)))
+            .collect::<Result<_, _>>()
+            .map(Term::Ids),
+        "status" => {
+            let mut statuses = Vec::new();
+            for v in values() {
+                match v.to_lowercase().as_str() {
+                    "open" => statuses.extend(Status::ALL.iter().filter(|s| !s.is_closed())),
+                    "closed" => statuses.extend(Status::ALL.iter().filter(|s| s.is_closed())),
+                    _ => statuses.push(v.parse()?),
+                }
+            }
+            Ok(Term::Status(statuses))
+        }
+        "priority" | "pri" => {
+            let scale = PriorityScale::load();
+            let mut levels = Vec::new();
+            for v in values() {
+                levels.extend(parse_priority_match(v)?.levels(&scale));
+            }
+            Ok(Term::Priority(levels))
+        }
+        "due" | "due.on" => match value.to_lowercase().as_str() {
+            "none" => Ok(Term::Due(DueMatch::None)),
+            "any" => Ok(Term::Due(DueMatch::Any)),
+            _ => Ok(Term::Due(DueMatch::On(parse_date(value, today)?))),
+        },
+        "due.before" => Ok(Term::Due(DueMatch::Before(parse_date(value, today)?))),
+        "due.after" => Ok(Term::Due(DueMatch::After(parse_date(value, today)?))),
+        "project" | "proj" => parse_project(value).map(Term::Project),
+        "tag" | "tags" => {
+            let patterns: Vec<String> = values().map(str::to_string).collect();
+            if patterns.is_empty() {
+                return Err("tag: needs a tag name".to_string());
+            }
+            Ok(Term::Tag(patterns))
+        }
+        "name" => Ok(Term::Text(TextField::Name, value.to_lowercase())),
+        "description" | "desc" => Ok(Term::Text(TextField::Description, value.to_lowercase())),
+        other => Err(format!(
+            "unknown filter `{}:`; use id, status, priority, due, due.before, due.after, project, tag, name or description",
+            other
+        )),
+    }
+}
+
+/// `+word` is a tag, except for the states `blocked` and `overdue`; use
+/// `tag:blocked` for a tag by that name.
+fn tag_or_state(word: &str) -> Term {
+    match word.to_lowercase().as_str() {
+        "blocked" => Term::Blocked,
+        "overdue" => Term::Overdue,
+        _ => Term::Tag(vec![word.to_string()]),
+    }
+}
+
+/// Reads a date in a query: `YYYY-MM-DD`, `today`, `tomorrow`,
+/// `yesterday`, a weekday (the next one after today), or a number of
+/// days or weeks from today like `3d`, `+2w` or `-1d`.
+fn parse_date(value: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let value = value.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    match value.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Days::new(1)),
+        "yesterday" => return Ok(today - Days::new(1)),
+        _ => {}
+    }
+    if let Ok(weekday) = value.parse::<Weekday>() {
+        let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        return Ok(today + Days::new(if ahead == 0 { 7 } else { ahead.into() }));
+    }
+
+    let (negative, amount) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(&value)),
+    };
+    let days = match amount.char_indices().last() {
+        Some((idx, 'd')) => amount[..idx].parse::<u64>().ok(),
+        Some((idx, 'w')) => amount[..idx].parse::<u64>().ok().map(|weeks| weeks * 7),
+        _ => None,
+    };
+    match days {
+        Some(days) if negative => Ok(today - Days::new(days)),
+        Some(days) => Ok(today + Days::new(days)),
+        None => Err(format!(
+            "can't read the date `{}`; use YYYY-MM-DD, today, tomorrow, a weekday or e.g. 3d",
+            value
+        )),
+    }
+}
+
+fn term_matches(term: &Term, task: &Task, data: &TaskData, today: NaiveDate) -> bool {
+    let due = || {
+        task.due_date
+            .as_deref()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    };
+    let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(needle);
+
+    match term {
+        Term::Ids(ids) => ids.contains(&task.id),
+        Term::Status(statuses) => statuses.contains(&task.status),
+        Term::Priority(levels) => levels
+            .iter()
+            .any(|p| p.as_str().eq_ignore_ascii_case(task.priority.as_str())),
+        Term::Due(DueMatch::On(date)) => due() == Some(*date),
+        Term::Due(DueMatch::Before(date)) => due().is_some_and(|d| d < *date),
+        Term::Due(DueMatch::After(date)) => due().is_some_and(|d| d > *date),
+        Term::Due(DueMatch::None) => task.due_date.is_none(),
+        Term::Due(DueMatch::Any) => task.due_date.is_some(),
+        Term::Project(ancestor) => task.project.as_deref().is_some_and(|p| in_project(p, ancestor)),
+        Term::Tag(patterns) => patterns
+            .iter()
+            .any(|pattern| task.tags.iter().any(|tag| TagFilter::tag_matches(tag, pattern))),
+        Term::Text(TextField::Name, needle) => contains(&task.name, needle),
+        Term::Text(TextField::Description, needle) => {
+            task.description.as_deref().is_some_and(|d| contains(d, needle))
+        }
+        Term::Text(TextField::Any, needle) => {
+            contains(&task.name, needle)
+                || task.description.as_deref().is_some_and(|d| contains(d, needle))
+                || task.annotations.iter().any(|a| contains(&a.text, needle))
+        }
+        Term::Blocked => {
+            !task.status.is_closed()
+                && (task.status == Status::Blocked || !open_prerequisites(data, task).is_empty())
+        }
+        Term::Overdue => !task.status.is_closed() && due().is_some_and(|d| d < today),
+        Term::Not(inner) => !term_matches(inner, task, data, today),
+    }
+}
+
+/// The IDs of the tasks a command given `query` should act on, or `None`
+/// if there are none or the user backs out. A plain list of IDs is taken
+/// as it is, so the command itself reports IDs that don't exist; with
+/// `confirm_ids` the tasks are listed and need confirming first. Anything
+/// else is matched against the open tasks (closed ones too with
+/// `include_closed`), and the matches always need confirming before
+/// `action` goes ahead, even a single one: a stray word is a text search
+/// and could pick a task the user never meant.
+pub fn select_targets(
+    query: &Query,
+    action: &str,
+    include_closed: bool,
+    confirm_ids: bool,
+) -> Option<Vec<usize>> {
+    if let Some(ids) = query.ids() {
+        if !confirm_ids {
+            return Some(ids.to_vec());
+        }
+    }
+
+    let data = load_or_exit();
+    let tasks = query.select(&data, include_closed);
+    if tasks.is_empty() {
+        println!("{}", "No tasks match".yellow());
+        return None;
+    }
+
+    let scale = PriorityScale::load();
+    println!();
+    for task in &tasks {
+        print_task_line(task, &scale, &[]);
+    }
+    println!();
+    let prompt = match tasks.len() {
+        1 => format!("{} this task?", action),
+        n => format!("{} these {} tasks?", action, n),
+    };
+    let confirmed = Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+    confirmed.then(|| tasks.iter().map(|t| t.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+
+    fn parse(text: &str) -> Result<Query, String> {
+        Query::parse_text(text)
+    }
+
+    fn words(words: &[&str]) -> Query {
+        Query::parse(&words.iter().map(|w| w.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn selected(query: &str, data: &TaskData) -> Vec<usize> {
+        parse(query).unwrap().select(data, false).iter().map(|t| t.id).collect()
+    }
+
+    /// A Wednesday.
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, 4).unwrap()
+    }
+
+    fn sample() -> TaskData {
+        let mut api = task(1, "Migrate the API");
+        api.tags = vec!["backend".to_string(), "work/api".to_string()];
+        api.due_date = Some("2025-06-01".to_string());
+        let mut docs = task(2, "Write docs");
+        docs.description = Some("Cover the db migration".to_string());
+        docs.status = Status::Waiting;
+        let mut done = task(3, "Old release");
+        done.status = Status::Done;
+        done.tags = vec!["backend".to_string()];
+        let mut deploy = task(4, "Deploy");
+        deploy.depends_on = vec![1];
+        TaskData::from_legacy(vec![api, docs, done, deploy])
+    }
+
+    #[test]
+    fn bare_ids_add_up() {
+        assert_eq!(words(&["3", "5", "id:7"]).terms, vec![Term::Ids(vec![3, 5, 7])]);
+        assert_eq!(parse("3 5").unwrap().ids(), Some(&[3, 5][..]));
+        assert_eq!(parse("3 +backend").unwrap().ids(), None);
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn closed_tasks_show_only_when_asked_for() {
+        let data = sample();
+        assert_eq!(selected("+backend", &data), vec![1]);
+        assert_eq!(selected("status:closed", &data), vec![3]);
+        assert_eq!(selected("status:open", &data), vec![1, 2, 4]);
+        assert_eq!(selected("3", &data), vec![3]);
+        assert_eq!(selected("-status:waiting", &data), vec![1, 4]);
+        assert!(!parse("-status:waiting").unwrap().selects_closed());
+        assert!(parse("status:done").unwrap().selects_closed());
+        assert_eq!(parse("+backend").unwrap().select(&data, true).len(), 2);
+    }
+
+    #[test]
+    fn matches_tags_text_and_states() {
+        let data = sample();
+        assert_eq!(selected("tag:work/*", &data), vec![1]);
+        assert_eq!(selected("-backend", &data), vec![2, 4]);
+        assert_eq!(selected("migration", &data), vec![2]);
+        assert_eq!(selected("name~migr", &data), vec![1]);
+        assert_eq!(selected(r#"description~"db migration""#, &data), vec![2]);
+        assert_eq!(selected("+blocked", &data), vec![4]);
+        assert_eq!(selected("+overdue", &data), vec![1]);
+        assert_eq!(selected("due:none -blocked", &data), vec![2]);
+        assert_eq!(selected("due.before:2025-06-02", &data), vec![1]);
+    }
+
+    #[test]
+    fn each_command_line_word_is_one_term() {
+        // `rtask ls name~"two words"` reaches rtask without the quotes.
+        let query = words(&["name~two words", "+api", r#"text~"db migration""#]);
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Text(TextField::Name, "two words".to_string()),
+                Term::Tag(vec!["api".to_string()]),
+                Term::Text(TextField::Any, "db migration".to_string()),
+            ]
+        );
+        assert!(words(&["", " "]).is_empty());
+        assert_eq!(parse("+a name~x").unwrap().terms.len(), 2);
+    }
+
+    #[test]
+    fn reads_relative_dates() {
+        let date = |value: &str| parse_date(value, today()).unwrap().to_string();
+        assert_eq!(date("today"), "2025-06-04");
+        assert_eq!(date("Tomorrow"), "2025-06-05");
+        assert_eq!(date("yesterday"), "2025-06-03");
+        assert_eq!(date("friday"), "2025-06-06");
+        assert_eq!(date("wed"), "2025-06-11");
+        assert_eq!(date("3d"), "2025-06-07");
+        assert_eq!(date("+2w"), "2025-06-18");
+        assert_eq!(date("-1d"), "2025-06-03");
+        assert!(parse_date("soon", today()).is_err());
+    }
+
+    #[test]
+    fn splits_words_outside_quotes() {
+        assert_eq!(tokenize(r#"name~"db migration"  +api"#), vec!["name~db migration", "+api"]);
+        assert_eq!(
+            parse_term("due.after:friday", today()),
+            Ok(Term::Due(DueMatch::After(NaiveDate::from_ymd_opt(2025, 6, 6).unwrap())))
+        );
+        assert_eq!(parse_term("+Overdue", today()), Ok(Term::Overdue));
+        assert_eq!(parse_term("tag:blocked", today()), Ok(Term::Tag(vec!["blocked".to_string()])));
+    }
+
+    #[test]
+    fn rejects_unknown_filters() {
+        assert!(parse("colour:red").unwrap_err().contains("unknown filter `colour:`"));
+        assert!(parse("size~big").is_err());
+        assert!(parse("id:x").is_err());
+        assert!(parse("tag:").is_err());
+        assert!(parse("status:someday").is_err());
+    }
+}
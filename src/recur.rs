@@ -0,0 +1,140 @@
+use chrono::{Local, NaiveDate};
+use colored::*;
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit};
+use crate::model::{RecurrenceRule, Task, TaskData};
+use crate::store::StoreError;
+
+/// After an occurrence of a series is completed, adds the next one with
+/// its due date worked out from the series' rule. Does nothing for a task
+/// outside a series, or when the series already has an open occurrence
+/// (e.g. a reopened task being completed again).
+pub fn spawn_next(data: &mut TaskData, id: usize) -> Option<Task> {
+    let task = data.find(id)?;
+    let uuid = task.series.clone()?;
+    let series = data.find_series(&uuid)?;
+
+    let already_open = data
+        .tasks
+        .iter()
+        .any(|t| t.id != id && t.series.as_deref() == Some(uuid.as_str()) && !t.status.is_closed());
+    if already_open {
+        return None;
+    }
+
+    let due = task
+        .due_date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let next_due = series.rule.next_due(due, Local::now().date_naive());
+
+    let mut next = series.occurrence(next_due);
+    next.id = data.allocate_id();
+    data.tasks.push(next.clone());
+    Some(next)
+}
+
+/// Finds the series `id` belongs to, rejecting tasks that don't recur.
+fn series_of(data: &TaskData, id: usize) -> Result<String, StoreError> {
+    let task = data.find(id).ok_or(StoreError::NotFound(id))?;
+    task.series
+        .clone()
+        .filter(|uuid| data.find_series(uuid).is_some())
+        .ok_or_else(|| StoreError::Rejected(format!("Task {} is not part of a recurring series", id)))
+}
+
+pub fn list_series() {
+    let data = load_or_exit();
+
+    if data.series.is_empty() {
+        println!("{}", "No recurring tasks".yellow());
+        return;
+    }
+
+    println!();
+    for series in &data.series {
+        let open = data
+            .tasks
+            .iter()
+            .find(|t| t.series.as_deref() == Some(series.uuid.as_str()) && !t.status.is_closed());
+        let completed = data
+            .tasks
+            .iter()
+            .filter(|t| t.series.as_deref() == Some(series.uuid.as_str()) && t.status.is_closed())
+            .count();
+
+        let next = match open {
+            Some(task) => format!(
+                "next: ID {}{}",
+                task.id,
+                task.due_date
+                    .as_ref()
+                    .map(|d| format!(", due {}", d))
+                    .unwrap_or_default()
+            ),
+            None => "no open occurrence".to_string(),
+        };
+
+        println!(
+            "{} {} {} {}",
+            format!("[{}]", series.rule).cyan(),
+            series.name.bright_white(),
+            format!("({})", next).yellow(),
+            format!("{} done", completed).green()
+        );
+    }
+    println!();
+}
+
+/// Changes the rule of the series task `id` belongs to. The open
+/// occurrence keeps its due date; the new rule applies from the next one.
+pub fn set_series_rule(id: usize, rule: RecurrenceRule) {
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        let uuid = series_of(data, id)?;
+        let series = data.find_series_mut(&uuid).ok_or(StoreError::NotFound(id))?;
+        series.rule = rule.clone();
+        Ok(series.name.clone())
+    });
+
+    match result {
+        Ok(name) => {
+            println!(
+                "{} {} {}",
+                "✓ Series now repeats".green().bold(),
+                rule.to_string().cyan(),
+                format!("({})", name).bright_white()
+            );
+        }
+        Err(e) => print_store_error("Error updating series:", &e),
+    }
+}
+
+/// Ends the series task `id` belongs to. Existing occurrences stay as
+/// ordinary tasks; completing them no longer creates new ones.
+pub fn stop_series(id: usize) {
+    let store = store_or_exit();
+
+    let result = store.modify(|data| {
+        let uuid = series_of(data, id)?;
+        data.series.retain(|s| s.uuid != uuid);
+        for task in &mut data.tasks {
+            if task.series.as_deref() == Some(uuid.as_str()) {
+                task.series = None;
+            }
+        }
+        Ok(data.find(id).map(|t| t.name.clone()).unwrap_or_default())
+    });
+
+    match result {
+        Ok(name) => {
+            println!(
+                "{} {}",
+                "✓ Stopped recurring series:".green().bold(),
+                name.bright_white()
+            );
+        }
+        Err(e) => print_store_error("Error stopping series:", &e),
+    }
+}
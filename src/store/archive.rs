@@ -0,0 +1,147 @@
+use chrono::{Datelike, Local, NaiveDate};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::json::{parse_task_data, write_atomic};
+use super::schema;
+use super::{StoreError, TaskFilter};
+use crate::model::{Task, TaskData};
+
+/// Closed tasks moved out of the active store, one `<year>.json` per year
+/// of completion in the same format as `tasks.json`. Nothing here is read
+/// unless a command asks for archived tasks.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+/// The year a closed task is filed under: when it was completed, or
+/// created if that is unknown.
+pub fn archive_year(task: &Task) -> i32 {
+    [task.completed_at.as_deref(), Some(task.created_at.as_str())]
+        .into_iter()
+        .flatten()
+        .find_map(|stamp| stamp.get(..4)?.parse().ok())
+        .unwrap_or_else(|| Local::now().year())
+}
+
+/// The day a task was completed or cancelled, if it is recorded.
+pub fn closed_on(task: &Task) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(task.completed_at.as_deref()?.get(..10)?, "%Y-%m-%d").ok()
+}
+
+impl Archive {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn year_path(&self, year: i32) -> PathBuf {
+        self.dir.join(format!("{}.json", year))
+    }
+
+    /// The years that have an archive file, oldest first.
+    pub fn years(&self) -> io::Result<Vec<i32>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut years: Vec<i32> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| path.file_stem()?.to_str()?.parse().ok())
+            .collect();
+        years.sort();
+        Ok(years)
+    }
+
+    pub fn read_year(&self, year: i32) -> Result<Vec<Task>, StoreError> {
+        let path = self.year_path(year);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&path)?;
+        let (data, _) = parse_task_data(&contents).map_err(|e| StoreError::Corrupt {
+            path: path.clone(),
+            quarantined: None,
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        })?;
+        Ok(data.tasks)
+    }
+
+    /// Every archived task matching `filter`, oldest year first.
+    pub fn list(&self, filter: &TaskFilter) -> Result<Vec<Task>, StoreError> {
+        let mut tasks = Vec::new();
+        for year in self.years()? {
+            tasks.extend(self.read_year(year)?.into_iter().filter(|t| filter.matches(t)));
+        }
+        Ok(tasks)
+    }
+
+    pub fn find(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        for year in self.years()?.into_iter().rev() {
+            if let Some(task) = self.read_year(year)?.into_iter().find(|t| t.id == id) {
+                return Ok(Some(task));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Files `tasks` under their years. A task already in the archive
+    /// (same ID) is replaced rather than added twice.
+    pub fn add(&self, tasks: &[Task]) -> Result<(), StoreError> {
+        let mut years: Vec<i32> = tasks.iter().map(archive_year).collect();
+        years.sort();
+        years.dedup();
+
+        fs::create_dir_all(&self.dir)?;
+        for year in years {
+            let incoming: Vec<&Task> = tasks.iter().filter(|t| archive_year(t) == year).collect();
+            let ids: HashSet<usize> = incoming.iter().map(|t| t.id).collect();
+
+            let mut filed = self.read_year(year)?;
+            filed.retain(|t| !ids.contains(&t.id));
+            filed.extend(incoming.into_iter().cloned());
+            filed.sort_by_key(|t| t.id);
+
+            let data = TaskData {
+                next_id: filed.last().map_or(1, |t| t.id + 1),
+                tasks: filed,
+                series: Vec::new(),
+            };
+            write_atomic(&self.year_path(year), schema::to_json(&data)?.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Takes the tasks with these IDs out of the archive, e.g. once they
+    /// are back in the store. IDs that aren't archived are ignored.
+    pub fn remove(&self, ids: &[usize]) -> Result<(), StoreError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        for year in self.years()? {
+            let mut filed = self.read_year(year)?;
+            let count = filed.len();
+            filed.retain(|t| !ids.contains(&t.id));
+            if filed.len() == count {
+                continue;
+            }
+
+            let path = self.year_path(year);
+            if filed.is_empty() {
+                fs::remove_file(&path)?;
+                continue;
+            }
+            let data = TaskData {
+                next_id: filed.last().map_or(1, |t| t.id + 1),
+                tasks: filed,
+                series: Vec::new(),
+            };
+            write_atomic(&path, schema::to_json(&data)?.as_bytes())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::json::{parse_task_data, write_atomic};
+use super::schema;
+use super::{StoreError, TaskFilter, TaskStore};
+use crate::model::{Task, TaskData};
+
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// One saved copy of the store, `<dir>/<id>.json`.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    /// The file stem: when it was taken, `YYYYMMDD-HHMMSS`, with a `-2`,
+    /// `-3`... suffix for several in the same second.
+    pub id: String,
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+}
+
+/// Rolling backups of the task store: the `recent` newest copies, plus the
+/// newest copy from each of the last `daily` days that had one. Everything
+/// older is pruned after each new backup. Both at zero turns backups off.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    dir: PathBuf,
+    recent: usize,
+    daily: usize,
+}
+
+impl Backups {
+    pub fn new(dir: impl Into<PathBuf>, recent: usize, daily: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            recent,
+            daily,
+        }
+    }
+
+    /// Every backup, newest first.
+    pub fn list(&self) -> io::Result<Vec<Backup>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups: Vec<Backup> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let id = path.file_stem()?.to_string_lossy().to_string();
+                let taken_at = NaiveDateTime::parse_from_str(id.get(..15)?, STAMP_FORMAT).ok()?;
+                Some(Backup { id, path, taken_at })
+            })
+            .collect();
+        let suffix = |b: &Backup| b.id.get(16..).and_then(|n| n.parse::<u32>().ok()).unwrap_or(1);
+        backups.sort_by_key(|b| Reverse((b.taken_at, suffix(b))));
+        Ok(backups)
+    }
+
+    /// Finds a backup by its ID, or by its position in [`Backups::list`]
+    /// counting from 1.
+    pub fn find(&self, id: &str) -> io::Result<Option<Backup>> {
+        let backups = self.list()?;
+        if let Ok(position) = id.parse::<usize>() {
+            if let Some(backup) = position.checked_sub(1).and_then(|idx| backups.get(idx)) {
+                return Ok(Some(backup.clone()));
+            }
+        }
+        Ok(backups.into_iter().find(|b| b.id == id))
+    }
+
+    pub fn read(&self, backup: &Backup) -> Result<TaskData, StoreError> {
+        let contents = fs::read_to_string(&backup.path)?;
+        if let Ok(version) = schema::detect_version(&contents) {
+            schema::check_supported(version)?;
+        }
+        let (data, _) = parse_task_data(&contents).map_err(|e| StoreError::Corrupt {
+            path: backup.path.clone(),
+            quarantined: None,
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        })?;
+        Ok(data)
+    }
+
+    /// Saves `data` as a new backup and returns its path, or `None` when
+    /// backups are off. Old backups stay until [`Backups::prune`].
+    pub fn save(&self, data: &TaskData) -> Result<Option<PathBuf>, StoreError> {
+        if self.recent == 0 && self.daily == 0 {
+            return Ok(None);
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        let stamp = Local::now().format(STAMP_FORMAT).to_string();
+        let mut path = self.dir.join(format!("{}.json", stamp));
+        let mut n = 2;
+        while path.exists() {
+            path = self.dir.join(format!("{}-{}.json", stamp, n));
+            n += 1;
+        }
+        write_atomic(&path, schema::to_json(data)?.as_bytes())?;
+        Ok(Some(path))
+    }
+
+    /// Removes the backups that are neither among the `recent` newest nor
+    /// the newest of one of the last `daily` days.
+    pub fn prune(&self) -> io::Result<()> {
+        let backups = self.list()?;
+        let mut days: HashSet<NaiveDate> = HashSet::new();
+
+        for (idx, backup) in backups.iter().enumerate() {
+            let day = backup.taken_at.date();
+            let daily_keeper = days.len() < self.daily && days.insert(day);
+            if idx >= self.recent && !daily_keeper {
+                fs::remove_file(&backup.path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wraps another store and backs up the data as it was before each
+/// transaction that changes it.
+pub struct BackedUpStore {
+    inner: Box<dyn TaskStore>,
+    backups: Backups,
+}
+
+impl BackedUpStore {
+    pub fn new(inner: Box<dyn TaskStore>, backups: Backups) -> Self {
+        Self { inner, backups }
+    }
+}
+
+impl TaskStore for BackedUpStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        self.inner.load()
+    }
+
+    fn check_schema(&self) -> Result<(), StoreError> {
+        self.inner.check_schema()
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        // The copy of the old data is written before the commit, so it
+        // exists even if the commit is cut short, but older backups are
+        // only pruned once the change has been committed.
+        let mut saved = None;
+        let result = self.inner.transaction(&mut |data| {
+            let before = data.clone();
+            f(data)?;
+
+            let empty = before.tasks.is_empty() && before.series.is_empty();
+            if !empty && serde_json::to_value(&before)? != serde_json::to_value(&*data)? {
+                saved = self.backups.save(&before)?;
+            }
+            Ok(())
+        });
+
+        match result {
+            Ok(()) if saved.is_some() => {
+                // The change is committed either way; a failed prune only
+                // leaves an extra backup until the next one.
+                let _ = self.backups.prune();
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+            Err(e) => {
+                if let Some(path) = saved {
+                    let _ = fs::remove_file(path);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn get(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        self.inner.get(id)
+    }
+
+    fn list(&self, filter: &TaskFilter) -> Result<Vec<Task>, StoreError> {
+        self.inner.list(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::{task, temp_dir};
+    use crate::store::memory::FailingStore;
+    use crate::store::MemoryStore;
+
+    fn rename(store: &dyn TaskStore, name: &str) -> Result<(), StoreError> {
+        store.transaction(&mut |data| {
+            data.find_mut(1).unwrap().name = name.to_string();
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn backs_up_the_data_from_before_each_change() {
+        let dir = temp_dir();
+        let backups = Backups::new(dir.join("backups"), 2, 0);
+        let store = BackedUpStore::new(Box::new(MemoryStore::with_tasks(vec![task(1, "a")])), backups.clone());
+
+        for name in ["b", "c", "d"] {
+            rename(&store, name).unwrap();
+        }
+        // Reading data back without a change takes no backup.
+        store.transaction(&mut |_| Ok(())).unwrap();
+
+        let list = backups.list().unwrap();
+        let names: Vec<String> = list
+            .iter()
+            .map(|b| backups.read(b).unwrap().tasks[0].name.clone())
+            .collect();
+        assert_eq!(names, vec!["c", "b"]);
+        assert_eq!(backups.find("1").unwrap().unwrap().id, list[0].id);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_write_keeps_the_existing_backups() {
+        let dir = temp_dir();
+        let backups = Backups::new(dir.join("backups"), 1, 0);
+        let good = BackedUpStore::new(Box::new(MemoryStore::with_tasks(vec![task(1, "a")])), backups.clone());
+        rename(&good, "b").unwrap();
+        let kept = backups.list().unwrap();
+
+        let failing = FailingStore(MemoryStore::with_tasks(vec![task(1, "b")]));
+        let store = BackedUpStore::new(Box::new(failing), backups.clone());
+        assert!(rename(&store, "c").is_err());
+
+        let list = backups.list().unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].id, kept[0].id);
+        assert_eq!(backups.read(&list[0]).unwrap().tasks[0].name, "a");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn no_backups_when_turned_off() {
+        let dir = temp_dir();
+        let backups = Backups::new(dir.join("backups"), 0, 0);
+        let store = BackedUpStore::new(Box::new(MemoryStore::with_tasks(vec![task(1, "a")])), backups.clone());
+        rename(&store, "b").unwrap();
+        assert!(backups.list().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
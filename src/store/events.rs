@@ -0,0 +1,604 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::json::{write_atomic, StoreLock};
+use super::{StoreError, TaskStore};
+use crate::model::{Series, Status, SubTask, Task, TaskData};
+
+/// A snapshot of the replayed state is written after this many events, so
+/// loading only has to replay what came after it.
+pub const SNAPSHOT_INTERVAL: u64 = 100;
+
+/// One change to the store. Replaying every event in order from an empty
+/// store gives the current tasks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Created {
+        task: Box<Task>,
+    },
+    /// A top-level field other than status or subtask completion changed.
+    Changed {
+        id: usize,
+        field: String,
+        from: Value,
+        to: Value,
+    },
+    StatusChanged {
+        id: usize,
+        from: Status,
+        to: Status,
+    },
+    SubtaskToggled {
+        id: usize,
+        subtask: usize,
+        completed: bool,
+    },
+    Removed {
+        id: usize,
+    },
+    SeriesChanged {
+        series: Vec<Series>,
+    },
+}
+
+impl Event {
+    /// The task the event is about, if it is about one.
+    pub fn task_id(&self) -> Option<usize> {
+        match self {
+            Event::Created { task } => Some(task.id),
+            Event::Changed { id, .. }
+            | Event::StatusChanged { id, .. }
+            | Event::SubtaskToggled { id, .. }
+            | Event::Removed { id } => Some(*id),
+            Event::SeriesChanged { .. } => None,
+        }
+    }
+
+    /// Applies the event to `data`, failing if it refers to a task or
+    /// subtask that isn't there.
+    pub fn apply(&self, data: &mut TaskData) -> Result<(), String> {
+        let missing = |id: &usize| format!("task {} doesn't exist", id);
+
+        match self {
+            Event::Created { task } => {
+                if data.find(task.id).is_some() {
+                    return Err(format!("task {} already exists", task.id));
+                }
+                data.next_id = data.next_id.max(task.id + 1);
+                data.tasks.push(task.as_ref().clone());
+            }
+            Event::Changed { id, field, to, .. } => {
+                let task = data.find_mut(*id).ok_or_else(|| missing(id))?;
+                let mut value = serde_json::to_value(&*task).map_err(|e| e.to_string())?;
+                if let Value::Object(fields) = &mut value {
+                    fields.insert(field.clone(), to.clone());
+                }
+                *task = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            }
+            Event::StatusChanged { id, to, .. } => {
+                data.find_mut(*id).ok_or_else(|| missing(id))?.status = *to;
+            }
+            Event::SubtaskToggled { id, subtask, completed } => {
+                let task = data.find_mut(*id).ok_or_else(|| missing(id))?;
+                let node = find_subtask_mut(&mut task.subtasks, *subtask)
+                    .ok_or_else(|| format!("task {} has no subtask {}", id, subtask))?;
+                node.completed = *completed;
+            }
+            Event::Removed { id } => {
+                let idx = data.position(*id).ok_or_else(|| missing(id))?;
+                data.tasks.remove(idx);
+            }
+            Event::SeriesChanged { series } => {
+                data.series = series.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_subtask_mut(nodes: &mut [SubTask], id: usize) -> Option<&mut SubTask> {
+    for node in nodes {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find_subtask_mut(&mut node.subtasks, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// The subtask tree with every node unchecked, to tell a toggle apart from
+/// a structural change.
+fn tree_shape(nodes: &[SubTask]) -> Vec<SubTask> {
+    nodes
+        .iter()
+        .map(|node| SubTask {
+            completed: false,
+            subtasks: tree_shape(&node.subtasks),
+            ..node.clone()
+        })
+        .collect()
+}
+
+/// The events that turn `before` into `after`.
+pub fn events_between(before: &TaskData, after: &TaskData) -> Result<Vec<Event>, StoreError> {
+    let mut events = Vec::new();
+
+    for task in &after.tasks {
+        let Some(old) = before.find(task.id) else {
+            events.push(Event::Created { task: Box::new(task.clone()) });
+            continue;
+        };
+        let (Value::Object(old_fields), Value::Object(new_fields)) =
+            (serde_json::to_value(old)?, serde_json::to_value(task)?)
+        else {
+            continue;
+        };
+
+        // Subtasks first and status last, so the history reads in the
+        // order things happen: a subtask is checked, progress moves, the
+        // task completes.
+        let mut changes = Vec::new();
+        let mut status = None;
+        for (field, to) in &new_fields {
+            let from = old_fields.get(field).cloned().unwrap_or(Value::Null);
+            if &from == to {
+                continue;
+            }
+            match field.as_str() {
+                "status" => {
+                    status = Some(Event::StatusChanged {
+                        id: task.id,
+                        from: old.status,
+                        to: task.status,
+                    })
+                }
+                "subtasks" if tree_shape(&old.subtasks) == tree_shape(&task.subtasks) => {
+                    for (was, now) in old.all_subtasks().into_iter().zip(task.all_subtasks()) {
+                        if was.completed != now.completed {
+                            events.push(Event::SubtaskToggled {
+                                id: task.id,
+                                subtask: now.id,
+                                completed: now.completed,
+                            });
+                        }
+                    }
+                }
+                _ => changes.push(Event::Changed {
+                    id: task.id,
+                    field: field.clone(),
+                    from,
+                    to: to.clone(),
+                }),
+            }
+        }
+        events.extend(changes);
+        events.extend(status);
+    }
+
+    for task in &before.tasks {
+        if after.find(task.id).is_none() {
+            events.push(Event::Removed { id: task.id });
+        }
+    }
+
+    if before.series != after.series {
+        events.push(Event::SeriesChanged {
+            series: after.series.clone(),
+        });
+    }
+    Ok(events)
+}
+
+/// A line of `events.jsonl`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventRecord {
+    pub seq: u64,
+    /// Local time, `YYYY-MM-DD HH:MM:SS`.
+    pub at: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// The replayed state as of event `seq`, which ends at byte `offset` of
+/// the log.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Snapshot {
+    seq: u64,
+    offset: u64,
+    data: TaskData,
+}
+
+/// What a replay found: the current data, the last event, and how far the
+/// log is intact.
+struct Replay {
+    data: TaskData,
+    seq: u64,
+    since_snapshot: u64,
+    end: u64,
+}
+
+/// Keeps every change as an event in the append-only `events.jsonl` and
+/// rebuilds the tasks by replaying it, starting from the latest snapshot in
+/// `events.snapshot.json`. Events are never rewritten, so the log doubles
+/// as a full history of every task.
+#[derive(Debug, Clone)]
+pub struct EventStore {
+    path: PathBuf,
+}
+
+impl EventStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.path.with_file_name("events.snapshot.json")
+    }
+
+    fn damaged(&self, message: String) -> StoreError {
+        StoreError::Backend(format!("{} is damaged: {}", self.path.display(), message))
+    }
+
+    /// Parses the complete lines of the log from byte `offset` on. A last
+    /// line without a newline is a write that was cut short and is left
+    /// out. Returns the events and the offset just past the last one.
+    fn read_from(&self, offset: u64) -> Result<(Vec<EventRecord>, u64), StoreError> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+            Err(e) => return Err(e.into()),
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let mut records = Vec::new();
+        let mut end = offset;
+        for line in contents.split_inclusive('\n') {
+            if !line.ends_with('\n') {
+                break;
+            }
+            if !line.trim().is_empty() {
+                let record = serde_json::from_str(line).map_err(|e| {
+                    self.damaged(format!("unreadable event at byte {}: {}", end, e))
+                })?;
+                records.push(record);
+            }
+            end += line.len() as u64;
+        }
+        Ok((records, end))
+    }
+
+    /// Every event in the log, oldest first.
+    pub fn events(&self) -> Result<Vec<EventRecord>, StoreError> {
+        Ok(self.read_from(0)?.0)
+    }
+
+    fn replay(&self) -> Result<Replay, StoreError> {
+        let log_len = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        let snapshot = match fs::read_to_string(self.snapshot_path()) {
+            Ok(contents) => serde_json::from_str::<Snapshot>(&contents)
+                .ok()
+                .filter(|s| s.offset <= log_len)
+                .unwrap_or_default(),
+            Err(_) => Snapshot::default(),
+        };
+
+        let (records, end) = self.read_from(snapshot.offset)?;
+        let mut data = snapshot.data;
+        let mut seq = snapshot.seq;
+        for record in &records {
+            record
+                .event
+                .apply(&mut data)
+                .map_err(|e| self.damaged(format!("can't replay event {}: {}", record.seq, e)))?;
+            seq = record.seq;
+        }
+        data.normalize();
+
+        Ok(Replay {
+            data,
+            seq,
+            since_snapshot: records.len() as u64,
+            end,
+        })
+    }
+
+    /// The sequence number of the last complete event and the offset just
+    /// past it, read from the end of the log so a long log isn't parsed.
+    fn tail(&self) -> Result<(u64, u64), StoreError> {
+        const CHUNK: u64 = 64 * 1024;
+        let len = match fs::metadata(&self.path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut start = len.saturating_sub(CHUNK);
+        loop {
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(start))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+
+            // Everything after the last newline is a write that was cut short.
+            let Some(end) = bytes.iter().rposition(|b| *b == b'\n') else {
+                if start == 0 {
+                    return Ok((0, 0));
+                }
+                start = start.saturating_sub(CHUNK);
+                continue;
+            };
+            let line_start = match bytes[..end].iter().rposition(|b| *b == b'\n') {
+                Some(idx) => idx + 1,
+                None if start == 0 => 0,
+                None => {
+                    start = start.saturating_sub(CHUNK);
+                    continue;
+                }
+            };
+            let record: EventRecord = serde_json::from_slice(&bytes[line_start..end]).map_err(|e| {
+                self.damaged(format!("unreadable event at byte {}: {}", start + line_start as u64, e))
+            })?;
+            return Ok((record.seq, start + end as u64 + 1));
+        }
+    }
+
+    /// Appends `events` after what is already in the log, without
+    /// replaying it. For a log kept as history next to another backend.
+    pub fn record(&self, events: Vec<Event>) -> Result<(), StoreError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let (seq, end) = self.tail()?;
+        self.append(seq, end, events)?;
+        Ok(())
+    }
+
+    fn append(&self, seq: u64, end: u64, events: Vec<Event>) -> Result<(u64, u64), StoreError> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut seq = seq;
+        let mut lines = String::new();
+        for event in events {
+            seq += 1;
+            let record = EventRecord {
+                seq,
+                at: at.clone(),
+                event,
+            };
+            lines.push_str(&serde_json::to_string(&record)?);
+            lines.push('\n');
+        }
+
+        let mut file = OpenOptions::new().create(true).truncate(false).write(true).open(&self.path)?;
+        // Drop the tail of a write that was cut short before adding to it.
+        file.set_len(end)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(lines.as_bytes())?;
+        file.sync_all()?;
+        Ok((seq, end + lines.len() as u64))
+    }
+}
+
+impl TaskStore for EventStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        Ok(self.replay()?.data)
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let _lock = StoreLock::acquire(&self.path)?;
+        let replay = self.replay()?;
+        let mut data = replay.data.clone();
+
+        f(&mut data)?;
+
+        let events = events_between(&replay.data, &data)?;
+        if events.is_empty() {
+            return Ok(());
+        }
+        let count = events.len() as u64;
+        let (seq, offset) = self.append(replay.seq, replay.end, events)?;
+
+        if replay.since_snapshot + count >= SNAPSHOT_INTERVAL {
+            let snapshot = Snapshot { seq, offset, data };
+            write_atomic(&self.snapshot_path(), serde_json::to_string(&snapshot)?.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps another backend and appends every change it makes to an event
+/// log, so `rtask history` has the same record whichever backend holds
+/// the tasks. The log is history only; the tasks are still read from the
+/// wrapped store.
+pub struct EventLoggedStore {
+    inner: Box<dyn TaskStore>,
+    log: EventStore,
+}
+
+impl EventLoggedStore {
+    pub fn new(inner: Box<dyn TaskStore>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            log: EventStore::new(path),
+        }
+    }
+}
+
+impl TaskStore for EventLoggedStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        self.inner.load()
+    }
+
+    fn check_schema(&self) -> Result<(), StoreError> {
+        self.inner.check_schema()
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        // The log stays locked until the events are written, so they go in
+        // the order their changes were committed.
+        let _lock = StoreLock::acquire(self.log.path())?;
+        let mut events = Vec::new();
+        self.inner.transaction(&mut |data| {
+            let before = data.clone();
+            f(data)?;
+            events = events_between(&before, data)?;
+            Ok(())
+        })?;
+
+        // Only changes the store committed go in the history.
+        self.log.record(events)
+    }
+
+    fn get(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        self.inner.get(id)
+    }
+
+    fn list(&self, filter: &super::TaskFilter) -> Result<Vec<Task>, StoreError> {
+        self.inner.list(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::{task, temp_dir};
+    use crate::store::memory::FailingStore;
+    use crate::store::MemoryStore;
+
+    fn subtask(id: usize, name: &str) -> SubTask {
+        SubTask { id, ..SubTask::new(name) }
+    }
+
+    fn tasks_json(data: &TaskData) -> Value {
+        serde_json::to_value(&data.tasks).unwrap()
+    }
+
+    #[test]
+    fn describes_each_kind_of_change() {
+        let mut with_subtasks = task(2, "ship");
+        with_subtasks.subtasks = vec![subtask(1, "build"), subtask(2, "test")];
+        let before = TaskData::from_legacy(vec![task(1, "old"), with_subtasks, task(3, "drop")]);
+
+        let mut after = before.clone();
+        after.find_mut(2).unwrap().subtasks[1].completed = true;
+        after.find_mut(2).unwrap().name = "ship it".to_string();
+        after.find_mut(2).unwrap().status = Status::InProgress;
+        after.tasks.retain(|t| t.id != 3);
+        after.tasks.push(task(4, "new"));
+
+        let events = events_between(&before, &after).unwrap();
+        let kinds: Vec<String> = events
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, vec!["subtask_toggled", "changed", "status_changed", "created", "removed"]);
+        assert!(matches!(&events[1], Event::Changed { id: 2, field, .. } if field == "name"));
+
+        let mut replayed = before.clone();
+        for event in &events {
+            event.apply(&mut replayed).unwrap();
+        }
+        replayed.tasks.sort_by_key(|t| t.id);
+        assert_eq!(tasks_json(&replayed), tasks_json(&after));
+    }
+
+    #[test]
+    fn a_reshaped_subtask_tree_is_one_change() {
+        let before = TaskData::from_legacy(vec![task(1, "a")]);
+        let mut after = before.clone();
+        after.find_mut(1).unwrap().subtasks.push(subtask(1, "step"));
+        let events = events_between(&before, &after).unwrap();
+        assert!(matches!(events.as_slice(), [Event::Changed { field, .. }] if field == "subtasks"));
+    }
+
+    #[test]
+    fn replaying_a_missing_task_fails() {
+        let mut data = TaskData::default();
+        assert!(Event::Removed { id: 7 }.apply(&mut data).is_err());
+        let created = Event::Created { task: Box::new(task(7, "a")) };
+        created.apply(&mut data).unwrap();
+        assert!(created.apply(&mut data).is_err());
+        assert_eq!(data.next_id, 8);
+    }
+
+    #[test]
+    fn the_log_replays_to_the_same_tasks() {
+        let dir = temp_dir();
+        let store = EventStore::new(dir.join("events.jsonl"));
+        let first = store.insert(task(0, "a")).unwrap();
+        store.insert(task(0, "b")).unwrap();
+        let mut done = first.clone();
+        done.status = Status::Done;
+        store.update(&done).unwrap();
+        store.delete(2).unwrap();
+
+        let data = store.load().unwrap();
+        assert_eq!(data.tasks.len(), 1);
+        assert_eq!(data.find(1).unwrap().status, Status::Done);
+        assert_eq!(store.events().unwrap().iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+
+        // A last line cut short is left out, and the next write replaces it.
+        let mut file = OpenOptions::new().append(true).open(store.path()).unwrap();
+        file.write_all(br#"{"seq":5,"at":"","type":"rem"#).unwrap();
+        assert_eq!(store.load().unwrap().tasks.len(), 1);
+        store.insert(task(0, "c")).unwrap();
+        assert_eq!(store.events().unwrap().last().unwrap().seq, 5);
+        assert_eq!(store.load().unwrap().tasks.len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_change_the_store_failed_to_write_is_not_logged() {
+        let dir = temp_dir();
+        let path = dir.join("history.jsonl");
+        let failing = FailingStore(MemoryStore::with_tasks(vec![task(1, "a")]));
+        let store = EventLoggedStore::new(Box::new(failing), &path);
+
+        assert!(store.delete(1).is_err());
+        assert!(EventStore::new(&path).events().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn records_history_for_another_backend() {
+        let dir = temp_dir();
+        let path = dir.join("history.jsonl");
+        let store = EventLoggedStore::new(Box::new(MemoryStore::with_tasks(vec![task(1, "a")])), &path);
+        store.insert(task(0, "b")).unwrap();
+        store.delete(1).unwrap();
+
+        let log = EventStore::new(&path);
+        let events = log.events().unwrap();
+        assert_eq!(events.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(matches!(events[1].event, Event::Removed { id: 1 }));
+        assert_eq!(store.load().unwrap().tasks.len(), 1);
+
+        log.record(vec![Event::Removed { id: 2 }]).unwrap();
+        assert_eq!(log.tail().unwrap().0, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
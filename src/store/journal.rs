@@ -0,0 +1,547 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::archive::Archive;
+use super::json::{quarantine, write_atomic, StoreLock};
+use super::{StoreError, TaskFilter, TaskStore};
+use crate::model::{timestamp, Series, Task, TaskData};
+
+/// How many operations `journal.json` remembers; older ones drop off.
+pub const JOURNAL_LIMIT: usize = 100;
+
+/// One task as it was before and after an operation. `None` on either
+/// side means the task didn't exist then.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskChange {
+    pub id: usize,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+impl TaskChange {
+    /// Top-level fields that differ between the two versions, leaving out
+    /// bookkeeping the user never edits directly.
+    pub fn changed_fields(&self) -> Vec<String> {
+        let (Some(before), Some(after)) = (&self.before, &self.after) else {
+            return Vec::new();
+        };
+        let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+            (serde_json::to_value(before), serde_json::to_value(after))
+        else {
+            return Vec::new();
+        };
+        after
+            .iter()
+            .filter(|(key, value)| key.as_str() != "reopen" && before.get(key.as_str()) != Some(value))
+            .map(|(key, _)| key.replace('_', " "))
+            .collect()
+    }
+}
+
+/// Everything one command changed, enough to apply it in either direction.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub at: String,
+    /// The command line that made the change.
+    pub command: String,
+    pub changes: Vec<TaskChange>,
+    /// Recurring series before and after, when the operation changed any.
+    #[serde(default)]
+    pub series: Option<(Vec<Series>, Vec<Series>)>,
+    /// Whether the tasks the operation removed went to the archive, so
+    /// undoing it takes them back out and redoing it files them again.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// Every task that differs between `before` and `after`, by ID.
+pub fn task_changes(before: &TaskData, after: &TaskData) -> Result<Vec<TaskChange>, StoreError> {
+    let mut changes = Vec::new();
+    for task in &after.tasks {
+        let old = before.find(task.id);
+        let changed = match old {
+            Some(old) => serde_json::to_value(old)? != serde_json::to_value(task)?,
+            None => true,
+        };
+        if changed {
+            changes.push(TaskChange {
+                id: task.id,
+                before: old.cloned(),
+                after: Some(task.clone()),
+            });
+        }
+    }
+    for task in &before.tasks {
+        if after.find(task.id).is_none() {
+            changes.push(TaskChange {
+                id: task.id,
+                before: Some(task.clone()),
+                after: None,
+            });
+        }
+    }
+    changes.sort_by_key(|c| c.id);
+    Ok(changes)
+}
+
+impl JournalEntry {
+    /// Compares the data before and after an operation. Returns `None`
+    /// when no task or series changed.
+    fn diff(
+        before: &TaskData,
+        after: &TaskData,
+        command: String,
+        archived: bool,
+    ) -> Result<Option<Self>, StoreError> {
+        let changes = task_changes(before, after)?;
+        let series = (before.series != after.series)
+            .then(|| (before.series.clone(), after.series.clone()));
+
+        if changes.is_empty() && series.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            seq: 0,
+            at: timestamp(),
+            command,
+            changes,
+            series,
+            archived,
+        }))
+    }
+
+    /// Puts `data` into the state on one side of this entry, provided it is
+    /// still in the state on the other side. If something outside the
+    /// journal (a hand edit, `rtask doctor`) changed the same tasks since,
+    /// nothing is applied.
+    fn apply(&self, data: &mut TaskData, direction: Direction) -> Result<(), StoreError> {
+        for change in &self.changes {
+            let (expected, target) = sides(change, direction);
+            let current = match data.find(change.id) {
+                Some(task) => Some(serde_json::to_value(task)?),
+                None => None,
+            };
+            let expected = expected.map(serde_json::to_value).transpose()?;
+            if current != expected {
+                return Err(StoreError::Rejected(format!(
+                    "Task {} has changed since; can't {} this operation",
+                    change.id, direction
+                )));
+            }
+
+            match (data.position(change.id), target) {
+                (Some(idx), Some(task)) => data.tasks[idx] = task.clone(),
+                (Some(idx), None) => {
+                    data.tasks.remove(idx);
+                }
+                (None, Some(task)) => {
+                    let idx = data.tasks.partition_point(|t| t.id < task.id);
+                    data.tasks.insert(idx, task.clone());
+                }
+                (None, None) => {}
+            }
+        }
+
+        if let Some((before, after)) = &self.series {
+            let (expected, target) = match direction {
+                Direction::Undo => (after, before),
+                Direction::Redo => (before, after),
+            };
+            if &data.series != expected {
+                return Err(StoreError::Rejected(format!(
+                    "Recurring series have changed since; can't {} this operation",
+                    direction
+                )));
+            }
+            data.series = target.clone();
+        }
+
+        data.normalize();
+        Ok(())
+    }
+}
+
+fn sides(change: &TaskChange, direction: Direction) -> (Option<&Task>, Option<&Task>) {
+    match direction {
+        Direction::Undo => (change.after.as_ref(), change.before.as_ref()),
+        Direction::Redo => (change.before.as_ref(), change.after.as_ref()),
+    }
+}
+
+fn command_line() -> String {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        "rtask (interactive)".to_string()
+    } else {
+        format!("rtask {}", args.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Undo,
+    Redo,
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Undo => f.write_str("undo"),
+            Direction::Redo => f.write_str("redo"),
+        }
+    }
+}
+
+/// The operations recorded in `journal.json`. The first `applied` entries
+/// are in effect and can be undone, newest last; the rest were undone and
+/// can be redone. Recording a new operation drops the redoable ones.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+    pub applied: usize,
+    #[serde(default)]
+    next_seq: u64,
+}
+
+impl Journal {
+    /// The entry `rtask undo` or `rtask redo` would apply next.
+    pub fn next(&self, direction: Direction) -> Option<&JournalEntry> {
+        match direction {
+            Direction::Undo => self.applied.checked_sub(1).and_then(|idx| self.entries.get(idx)),
+            Direction::Redo => self.entries.get(self.applied),
+        }
+    }
+
+    fn record(&mut self, mut entry: JournalEntry) {
+        self.entries.truncate(self.applied);
+        self.next_seq = self.next_seq.max(self.entries.last().map_or(0, |e| e.seq)) + 1;
+        entry.seq = self.next_seq;
+        self.entries.push(entry);
+        if self.entries.len() > JOURNAL_LIMIT {
+            let excess = self.entries.len() - JOURNAL_LIMIT;
+            self.entries.drain(..excess);
+        }
+        self.applied = self.entries.len();
+    }
+}
+
+/// Wraps another store and records every transaction that changes
+/// something in a journal, so it can be undone and redone later.
+pub struct JournaledStore {
+    inner: Box<dyn TaskStore>,
+    path: PathBuf,
+    label: Option<String>,
+    archiving: bool,
+}
+
+impl JournaledStore {
+    pub fn new(inner: Box<dyn TaskStore>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            label: None,
+            archiving: false,
+        }
+    }
+
+    /// Records changes under `label` instead of the command line, for work
+    /// rtask does on its own while running some other command.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Marks the changes as moving the tasks they remove to the archive
+    /// next to the journal, which undo and redo then keep in step.
+    pub fn archiving(mut self) -> Self {
+        self.archiving = true;
+        self
+    }
+
+    fn archive(&self) -> Archive {
+        Archive::new(self.path.with_file_name("archive"))
+    }
+
+    /// After undoing or redoing an archive run, takes the tasks that are
+    /// back in the store out of the archive, or files the ones that left
+    /// it again, so no task is in both places.
+    fn sync_archive(&self, entry: &JournalEntry, direction: Direction) -> Result<(), StoreError> {
+        let mut filed = Vec::new();
+        let mut restored = Vec::new();
+        for change in &entry.changes {
+            match sides(change, direction) {
+                (Some(task), None) => filed.push(task.clone()),
+                (None, Some(task)) => restored.push(task.id),
+                _ => {}
+            }
+        }
+        let archive = self.archive();
+        if !filed.is_empty() {
+            archive.add(&filed)?;
+        }
+        archive.remove(&restored)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the journal. A journal that can't be parsed is set aside as a
+    /// `.corrupt` copy and treated as empty, so it never blocks changes to
+    /// the tasks themselves.
+    pub fn journal(&self) -> Result<Journal, StoreError> {
+        if !self.path.exists() {
+            return Ok(Journal::default());
+        }
+        let contents = fs::read_to_string(&self.path)?;
+        match serde_json::from_str(&contents) {
+            Ok(journal) => Ok(journal),
+            Err(_) => {
+                quarantine(&self.path, &contents)?;
+                Ok(Journal::default())
+            }
+        }
+    }
+
+    fn write_journal(&self, journal: &Journal) -> Result<(), StoreError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(journal)?;
+        write_atomic(&self.path, json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Undoes or redoes the next entry, provided it is still the one with
+    /// sequence number `seq` (i.e. nothing was recorded since the caller
+    /// looked). Returns the entry that was applied. The journal only moves
+    /// once the store has committed the change.
+    pub fn step(&self, direction: Direction, seq: u64) -> Result<JournalEntry, StoreError> {
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut journal = self.journal()?;
+        let entry = journal
+            .next(direction)
+            .filter(|e| e.seq == seq)
+            .cloned()
+            .ok_or_else(|| {
+                StoreError::Rejected(format!("Nothing to {} any more; the journal changed", direction))
+            })?;
+
+        self.inner.transaction(&mut |data| entry.apply(data, direction))?;
+        if entry.archived {
+            self.sync_archive(&entry, direction)?;
+        }
+        match direction {
+            Direction::Undo => journal.applied -= 1,
+            Direction::Redo => journal.applied += 1,
+        }
+        self.write_journal(&journal)?;
+        Ok(entry)
+    }
+}
+
+impl TaskStore for JournaledStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        self.inner.load()
+    }
+
+    fn check_schema(&self) -> Result<(), StoreError> {
+        self.inner.check_schema()
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        // The journal stays locked until the entry is written, so entries
+        // go in the order their changes were committed.
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut recorded = None;
+        self.inner.transaction(&mut |data| {
+            let before = data.clone();
+            f(data)?;
+
+            let command = self.label.clone().unwrap_or_else(command_line);
+            recorded = JournalEntry::diff(&before, data, command, self.archiving)?;
+            Ok(())
+        })?;
+
+        // Only a change the store committed goes in the journal.
+        if let Some(entry) = recorded {
+            let mut journal = self.journal()?;
+            journal.record(entry);
+            self.write_journal(&journal)?;
+        }
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        self.inner.get(id)
+    }
+
+    fn list(&self, filter: &TaskFilter) -> Result<Vec<Task>, StoreError> {
+        self.inner.list(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::{task, temp_dir};
+    use crate::model::Status;
+    use crate::store::memory::FailingStore;
+    use crate::store::MemoryStore;
+
+    fn journaled(tasks: Vec<Task>) -> (JournaledStore, PathBuf) {
+        let dir = temp_dir();
+        let store = JournaledStore::new(Box::new(MemoryStore::with_tasks(tasks)), dir.join("journal.json"));
+        (store, dir)
+    }
+
+    #[test]
+    fn undo_and_redo_restore_each_side() {
+        let before = TaskData::from_legacy(vec![task(1, "keep"), task(2, "drop")]);
+        let mut after = before.clone();
+        after.find_mut(1).unwrap().status = Status::Done;
+        after.tasks.retain(|t| t.id != 2);
+        after.tasks.push(task(3, "new"));
+
+        let entry = JournalEntry::diff(&before, &after, "test".to_string(), false).unwrap().unwrap();
+        assert_eq!(entry.changes.iter().map(|c| c.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let mut data = after.clone();
+        entry.apply(&mut data, Direction::Undo).unwrap();
+        assert_eq!(serde_json::to_value(&data.tasks).unwrap(), serde_json::to_value(&before.tasks).unwrap());
+        entry.apply(&mut data, Direction::Redo).unwrap();
+        assert_eq!(serde_json::to_value(&data.tasks).unwrap(), serde_json::to_value(&after.tasks).unwrap());
+    }
+
+    #[test]
+    fn no_entry_without_changes() {
+        let data = TaskData::from_legacy(vec![task(1, "same")]);
+        assert!(JournalEntry::diff(&data, &data, "test".to_string(), false).unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_to_undo_over_a_later_change() {
+        let before = TaskData::from_legacy(vec![task(1, "a")]);
+        let mut after = before.clone();
+        after.find_mut(1).unwrap().name = "b".to_string();
+        let entry = JournalEntry::diff(&before, &after, "test".to_string(), false).unwrap().unwrap();
+
+        let mut edited = after.clone();
+        edited.find_mut(1).unwrap().name = "c".to_string();
+        assert!(matches!(entry.apply(&mut edited, Direction::Undo), Err(StoreError::Rejected(_))));
+        assert_eq!(edited.find(1).unwrap().name, "c");
+    }
+
+    #[test]
+    fn recording_drops_redoable_entries_and_old_ones() {
+        let mut journal = Journal::default();
+        let entry = |n: usize| JournalEntry {
+            seq: 0,
+            at: String::new(),
+            command: format!("change {}", n),
+            changes: Vec::new(),
+            series: None,
+            archived: false,
+        };
+        journal.record(entry(1));
+        journal.record(entry(2));
+        journal.applied = 1;
+        journal.record(entry(3));
+        let commands: Vec<&str> = journal.entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["change 1", "change 3"]);
+        assert_eq!(journal.next(Direction::Undo).map(|e| e.seq), Some(3));
+        assert!(journal.next(Direction::Redo).is_none());
+
+        for n in 0..JOURNAL_LIMIT {
+            journal.record(entry(n));
+        }
+        assert_eq!(journal.entries.len(), JOURNAL_LIMIT);
+        assert_eq!(journal.applied, JOURNAL_LIMIT);
+    }
+
+    #[test]
+    fn steps_back_and_forth_through_a_store() {
+        let (store, dir) = journaled(vec![task(1, "a")]);
+        store
+            .transaction(&mut |data| {
+                data.find_mut(1).unwrap().name = "b".to_string();
+                Ok(())
+            })
+            .unwrap();
+        let seq = store.journal().unwrap().next(Direction::Undo).unwrap().seq;
+
+        store.step(Direction::Undo, seq).unwrap();
+        assert_eq!(store.get(1).unwrap().unwrap().name, "a");
+        assert!(store.step(Direction::Undo, seq).is_err());
+
+        store.step(Direction::Redo, seq).unwrap();
+        assert_eq!(store.get(1).unwrap().unwrap().name, "b");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_change_the_store_failed_to_write_is_not_journaled() {
+        let dir = temp_dir();
+        let failing = FailingStore(MemoryStore::with_tasks(vec![task(1, "a")]));
+        let store = JournaledStore::new(Box::new(failing), dir.join("journal.json"));
+
+        let result = store.transaction(&mut |data| {
+            data.find_mut(1).unwrap().name = "b".to_string();
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(store.journal().unwrap().entries.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_undo_leaves_the_journal_where_it_was() {
+        let (store, dir) = journaled(vec![task(1, "a")]);
+        store
+            .transaction(&mut |data| {
+                data.find_mut(1).unwrap().name = "b".to_string();
+                Ok(())
+            })
+            .unwrap();
+        let seq = store.journal().unwrap().next(Direction::Undo).unwrap().seq;
+
+        let failing = FailingStore(MemoryStore::with_tasks(store.load().unwrap().tasks));
+        let store = JournaledStore::new(Box::new(failing), store.path().to_path_buf());
+        assert!(store.step(Direction::Undo, seq).is_err());
+        assert_eq!(store.journal().unwrap().applied, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn undoing_an_archive_run_takes_the_tasks_back_out() {
+        let mut done = task(1, "done");
+        done.complete();
+        let (store, dir) = journaled(vec![done.clone(), task(2, "open")]);
+        let store = store.archiving();
+        let archive = Archive::new(dir.join("archive"));
+
+        store
+            .transaction(&mut |data| {
+                archive.add(&[done.clone()])?;
+                data.tasks.retain(|t| t.id != 1);
+                Ok(())
+            })
+            .unwrap();
+        let seq = store.journal().unwrap().next(Direction::Undo).unwrap().seq;
+
+        store.step(Direction::Undo, seq).unwrap();
+        assert!(store.get(1).unwrap().is_some());
+        assert!(archive.find(1).unwrap().is_none());
+
+        store.step(Direction::Redo, seq).unwrap();
+        assert!(store.get(1).unwrap().is_none());
+        assert!(archive.find(1).unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,364 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::Local;
+
+use serde_json::Value;
+
+use super::schema::{self, CURRENT_SCHEMA_VERSION};
+use super::{StoreError, TaskStore};
+use crate::model::TaskData;
+
+/// Parses the contents of `tasks.json`, upgrading older layouts to the
+/// current schema. Also returns the schema version the file was written
+/// with.
+pub fn parse_task_data(contents: &str) -> Result<(TaskData, u32), serde_json::Error> {
+    let version = schema::detect_version(contents)?;
+
+    let mut data: TaskData = if version < CURRENT_SCHEMA_VERSION {
+        let doc: Value = serde_json::from_str(contents)?;
+        serde_json::from_value(schema::migrate(doc, version))?
+    } else {
+        serde_json::from_str(contents)?
+    };
+    data.normalize();
+    Ok((data, version))
+}
+
+/// The default backend: every task in one pretty-printed JSON file.
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the file without taking the lock. Saves replace the file with
+    /// a rename, so a reader always sees one complete version.
+    pub fn read(&self) -> Result<TaskData, StoreError> {
+        Ok(self.read_versioned()?.0)
+    }
+
+    /// Reads the file along with the schema version it was written with.
+    /// A file in an older layout is copied to `tasks.json.v<N>.bak` before
+    /// it is upgraded, so the pre-migration data survives the next save.
+    pub fn read_versioned(&self) -> Result<(TaskData, u32), StoreError> {
+        if !self.path.exists() {
+            return Ok((TaskData::default(), CURRENT_SCHEMA_VERSION));
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        // A newer rtask may have changed the layout. Such a file is listed
+        // if it still parses, but never reported as corrupt or quarantined.
+        if let Ok(version) = schema::detect_version(&contents) {
+            if let Err(newer) = schema::check_supported(version) {
+                let (data, _) = parse_task_data(&contents).map_err(|_| newer)?;
+                return Ok((data, version));
+            }
+        }
+
+        let (data, version) = parse_task_data(&contents).map_err(|e| StoreError::Corrupt {
+            quarantined: quarantine(&self.path, &contents).ok(),
+            path: self.path.clone(),
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        })?;
+
+        if version < CURRENT_SCHEMA_VERSION {
+            let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+            let backup = self.path.with_file_name(format!("{}.v{}.bak", file_name, version));
+            if !backup.exists() {
+                fs::write(&backup, &contents)?;
+            }
+        }
+        Ok((data, version))
+    }
+
+    /// Writes the store atomically: the JSON goes to a temporary file in
+    /// the same directory, is synced to disk and then renamed over the
+    /// store, so a crash mid-write leaves either the old or the new file,
+    /// never a truncated one. Refuses to replace a file written with a
+    /// newer schema. Callers doing a read-modify-write should hold a
+    /// [`StoreLock`] across the read and the write.
+    pub fn write(&self, data: &TaskData) -> Result<(), StoreError> {
+        self.check_schema()?;
+        Ok(self.write_unchecked(data)?)
+    }
+
+    fn write_unchecked(&self, data: &TaskData) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let json = schema::to_json(data)?;
+        write_atomic(&self.path, json.as_bytes())
+    }
+
+    pub fn lock(&self) -> Result<StoreLock, StoreError> {
+        StoreLock::acquire(&self.path)
+    }
+}
+
+impl TaskStore for JsonStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        self.read()
+    }
+
+    /// Only the version marker is checked; the tasks aren't built. A file
+    /// too damaged for even that passes, so `rtask doctor` can still
+    /// repair it.
+    fn check_schema(&self) -> Result<(), StoreError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        match schema::detect_version(&contents) {
+            Ok(version) => schema::check_supported(version),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let _lock = self.lock()?;
+        let (mut data, version) = self.read_versioned()?;
+        schema::check_supported(version)?;
+        let before = serde_json::to_value(&data)?;
+
+        f(&mut data)?;
+
+        if serde_json::to_value(&data)? != before {
+            self.write_unchecked(&data)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(contents)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // Make the rename itself durable. Directories can't be opened this way
+    // on every platform, so this is best effort.
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An advisory, cross-process lock on the task store, held on
+/// `tasks.json.lock` until dropped. The lock file records the holder's pid
+/// so a blocked command can say who it is waiting for; the OS releases the
+/// lock itself when the holder exits, so a leftover lock file is harmless.
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: File,
+}
+
+impl StoreLock {
+    pub fn acquire(store_path: &Path) -> Result<StoreLock, StoreError> {
+        let file_name = store_path.file_name().unwrap_or_default().to_string_lossy();
+        let path = store_path.with_file_name(format!("{}.lock", file_name));
+
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let started = Instant::now();
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    if started.elapsed() >= LOCK_TIMEOUT {
+                        return Err(StoreError::Locked {
+                            pid: read_lock_pid(&mut file),
+                        });
+                    }
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+
+        Ok(StoreLock { _file: file })
+    }
+}
+
+fn read_lock_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// Keeps a timestamped copy of a store that failed to parse, so nothing a
+/// later repair does can lose the original bytes. A file that was already
+/// quarantined with identical contents is not copied again.
+pub fn quarantine(path: &Path, contents: &str) -> io::Result<PathBuf> {
+    if let Some(existing) = find_quarantined(path)?
+        .into_iter()
+        .find(|copy| fs::read_to_string(copy).map(|c| c == contents).unwrap_or(false))
+    {
+        return Ok(existing);
+    }
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let copy = path.with_file_name(format!("{}.{}.corrupt", file_name, stamp));
+    fs::write(&copy, contents)?;
+    Ok(copy)
+}
+
+pub fn find_quarantined(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = match path.parent() {
+        Some(dir) if dir.exists() => dir,
+        _ => return Ok(Vec::new()),
+    };
+    let prefix = format!("{}.", path.file_name().unwrap_or_default().to_string_lossy());
+
+    let mut copies: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(&prefix) && name.ends_with(".corrupt")
+        })
+        .collect();
+    copies.sort();
+    Ok(copies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::{task, temp_dir};
+
+    fn quarantined(store: &JsonStore) -> usize {
+        find_quarantined(store.path()).unwrap().len()
+    }
+
+    #[test]
+    fn a_corrupt_file_is_reported_and_quarantined_but_never_overwritten() {
+        let dir = temp_dir();
+        let store = JsonStore::new(dir.join("tasks.json"));
+        let broken = "{\n  \"next_id\": 2,\n  \"tasks\": [\n    {\"id\": 1,}\n";
+        fs::write(store.path(), broken).unwrap();
+
+        match store.load() {
+            Err(StoreError::Corrupt { line, quarantined: Some(copy), .. }) => {
+                assert_eq!(line, 4);
+                assert_eq!(fs::read_to_string(copy).unwrap(), broken);
+            }
+            other => panic!("expected a corrupt store, got {:?}", other.map(|d| d.tasks.len())),
+        }
+        assert!(matches!(store.insert(task(0, "a")), Err(StoreError::Corrupt { .. })));
+        assert_eq!(fs::read_to_string(store.path()).unwrap(), broken);
+        // Reading the same broken file again doesn't pile up copies.
+        assert_eq!(quarantined(&store), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_replace_the_file_whole() {
+        let dir = temp_dir();
+        let path = dir.join("tasks.json");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        let names: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, vec!["tasks.json"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_lock_is_held_until_dropped_and_names_its_holder() {
+        let dir = temp_dir();
+        let store = JsonStore::new(dir.join("tasks.json"));
+        let lock_path = dir.join("tasks.json.lock");
+
+        let lock = store.lock().unwrap();
+        let other = File::open(&lock_path).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+        assert_eq!(fs::read_to_string(&lock_path).unwrap(), std::process::id().to_string());
+
+        drop(lock);
+        other.try_lock().unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_a_newer_file_it_cannot_parse_without_quarantining_it() {
+        let dir = temp_dir();
+        let store = JsonStore::new(dir.join("tasks.json"));
+        let newer = r#"{"schema_version": 99, "tasks": {"format": "something new"}}"#;
+        fs::write(store.path(), newer).unwrap();
+
+        assert!(matches!(store.load(), Err(StoreError::NewerSchema { found: 99, .. })));
+        assert!(matches!(store.insert(task(0, "a")), Err(StoreError::NewerSchema { .. })));
+        assert_eq!(quarantined(&store), 0);
+        assert_eq!(fs::read_to_string(store.path()).unwrap(), newer);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lists_a_newer_file_it_can_parse_but_never_writes_it() {
+        let dir = temp_dir();
+        let store = JsonStore::new(dir.join("tasks.json"));
+        let data = TaskData::from_legacy(vec![task(1, "a")]);
+        let mut doc: Value = serde_json::from_str(&schema::to_json(&data).unwrap()).unwrap();
+        doc["schema_version"] = (CURRENT_SCHEMA_VERSION + 1).into();
+        fs::write(store.path(), doc.to_string()).unwrap();
+
+        assert_eq!(store.load().unwrap().tasks.len(), 1);
+        assert!(matches!(store.check_schema(), Err(StoreError::NewerSchema { .. })));
+        assert!(store.write(&data).is_err());
+        assert!(store.delete(1).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+
+use super::{StoreError, TaskStore};
+use crate::model::{Task, TaskData};
+
+/// A store that lives only in memory, for tests and for embedding rtask
+/// in programs that manage persistence themselves.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: Mutex<TaskData>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tasks(tasks: Vec<Task>) -> Self {
+        Self {
+            data: Mutex::new(TaskData::from_legacy(tasks)),
+        }
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        Ok(self.data.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let mut guard = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = guard.clone();
+        f(&mut data)?;
+        *guard = data;
+        Ok(())
+    }
+}
+
+/// A store whose writes always fail after the transaction body has run,
+/// like a full disk, for testing the layers that wrap a backend.
+#[cfg(test)]
+pub(crate) struct FailingStore(pub MemoryStore);
+
+#[cfg(test)]
+impl TaskStore for FailingStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        self.0.load()
+    }
+
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let mut data = self.0.load()?;
+        f(&mut data)?;
+        Err(StoreError::Backend("no space left on device".to_string()))
+    }
+}
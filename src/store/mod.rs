@@ -0,0 +1,437 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::model::{Priority, Status, StorageBackend, Task, TaskData, get_backups_dir, get_history_file, get_journal_file, get_list_dir, get_store_dir, in_project, load_config, new_uuid};
+
+pub mod archive;
+pub mod backup;
+pub mod events;
+pub mod journal;
+pub mod json;
+pub mod memory;
+pub mod schema;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use backup::{BackedUpStore, Backups};
+pub use events::{EventLoggedStore, EventStore};
+pub use journal::JournaledStore;
+pub use json::{JsonStore, StoreLock};
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    NotFound(usize),
+    /// A transaction body refused to apply its change; the message says why.
+    Rejected(String),
+    /// The backend itself failed, or isn't available in this build.
+    Backend(String),
+    Locked {
+        pid: Option<u32>,
+    },
+    /// The store was written by a newer rtask with a schema this build
+    /// doesn't know, so writing it could drop data.
+    NewerSchema {
+        found: u32,
+        supported: u32,
+    },
+    Corrupt {
+        path: PathBuf,
+        quarantined: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{}", e),
+            StoreError::NotFound(id) => write!(f, "Task with ID {} not found", id),
+            StoreError::Rejected(reason) => write!(f, "{}", reason),
+            StoreError::Backend(message) => write!(f, "{}", message),
+            StoreError::Locked { pid } => {
+                match pid {
+                    Some(pid) => write!(f, "task store is locked by pid {}", pid)?,
+                    None => write!(f, "task store is locked by another process")?,
+                }
+                write!(f, " (gave up after {}s)", json::LOCK_TIMEOUT.as_secs())
+            }
+            StoreError::NewerSchema { found, supported } => write!(
+                f,
+                "the task store was written by a newer rtask (schema version {}, this build \
+                 supports up to {}); upgrade rtask before changing tasks",
+                found, supported
+            ),
+            StoreError::Corrupt { path, quarantined, line, column, message } => {
+                write!(
+                    f,
+                    "{} is corrupt at line {}, column {}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    message
+                )?;
+                if let Some(copy) = quarantined {
+                    write!(f, " (a copy was saved to {})", copy.display())?;
+                }
+                write!(f, ". Run `rtask doctor` to repair it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError::Io(e.into())
+    }
+}
+
+/// Which tags a task must, or must not, carry. A pattern matches a tag
+/// exactly, or every tag starting with it if it ends in `*`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagFilter {
+    /// A task needs a tag matching at least one pattern of every group.
+    pub all_of: Vec<Vec<String>>,
+    /// A task with a tag matching any of these is left out.
+    pub none_of: Vec<String>,
+}
+
+impl TagFilter {
+    /// Builds a filter from terms such as `backend`, `tag:backend`,
+    /// `api,web` (either tag) and `-tag:someday` or `!someday` (not that
+    /// tag). Every term must hold.
+    pub fn parse(terms: &[String]) -> Self {
+        let mut filter = Self::default();
+        for term in terms {
+            let term = term.trim();
+            let (negated, term) = match term.strip_prefix('-').or_else(|| term.strip_prefix('!')) {
+                Some(rest) => (true, rest),
+                None => (false, term.strip_prefix('+').unwrap_or(term)),
+            };
+            let term = term.strip_prefix("tag:").unwrap_or(term);
+            let patterns: Vec<String> = term
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+
+            if patterns.is_empty() {
+                continue;
+            }
+            if negated {
+                filter.none_of.extend(patterns);
+            } else {
+                filter.all_of.push(patterns);
+            }
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all_of.is_empty() && self.none_of.is_empty()
+    }
+
+    /// Whether `tag` matches `pattern`.
+    pub fn tag_matches(tag: &str, pattern: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => tag.starts_with(prefix),
+            None => tag == pattern,
+        }
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        let has = |pattern: &String| tags.iter().any(|tag| Self::tag_matches(tag, pattern));
+        self.all_of.iter().all(|group| group.iter().any(has)) && !self.none_of.iter().any(has)
+    }
+}
+
+/// Criteria for [`TaskStore::list`]. Every field that is set must match.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    /// `Some(true)` keeps only done or cancelled tasks, `Some(false)` only
+    /// the ones still open.
+    pub closed: Option<bool>,
+    /// Keeps tasks in exactly this status.
+    pub status: Option<Status>,
+    /// Keeps tasks whose priority is one of these levels.
+    pub priorities: Option<Vec<Priority>>,
+    /// Keeps tasks whose tags match.
+    pub tags: TagFilter,
+    /// Keeps tasks in this project or any project below it.
+    pub project: Option<String>,
+    /// Keeps tasks due on this `YYYY-MM-DD` date.
+    pub due_on: Option<String>,
+    /// Keeps tasks whose name, description or annotations contain this
+    /// text, ignoring case.
+    pub search: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &Task) -> bool {
+        let closed_match = match self.closed {
+            Some(closed) => task.status.is_closed() == closed,
+            None => true,
+        };
+        let status_match = self.status.is_none_or(|status| task.status == status);
+
+        let priority_match = match &self.priorities {
+            Some(priorities) => priorities
+                .iter()
+                .any(|p| p.as_str().eq_ignore_ascii_case(task.priority.as_str())),
+            None => true,
+        };
+
+        let tag_match = self.tags.matches(&task.tags);
+
+        let project_match = match &self.project {
+            Some(ancestor) => task.project.as_deref().is_some_and(|p| in_project(p, ancestor)),
+            None => true,
+        };
+
+        let due_match = match &self.due_on {
+            Some(date) => task.due_date.as_ref() == Some(date),
+            None => true,
+        };
+
+        let search_match = match &self.search {
+            Some(text) => {
+                let needle = text.to_lowercase();
+                let contains = |haystack: &str| haystack.to_lowercase().contains(&needle);
+                contains(&task.name)
+                    || task.description.as_deref().is_some_and(contains)
+                    || task.annotations.iter().any(|a| contains(&a.text))
+            }
+            None => true,
+        };
+
+        closed_match
+            && status_match
+            && priority_match
+            && tag_match
+            && project_match
+            && due_match
+            && search_match
+    }
+}
+
+/// Storage backend for tasks.
+///
+/// Implementors provide [`load`](TaskStore::load) and
+/// [`transaction`](TaskStore::transaction); the record-level methods have
+/// default implementations on top of those, which backends with real
+/// indexes can override.
+pub trait TaskStore {
+    /// Reads the whole store.
+    fn load(&self) -> Result<TaskData, StoreError>;
+
+    /// Runs `f` once against the current data with exclusive access to the
+    /// store and persists the result if `f` succeeds. When `f` returns an
+    /// error nothing is written and the error is passed through.
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError>;
+
+    /// Fails with [`StoreError::NewerSchema`] if a newer rtask wrote the
+    /// store in a schema this build doesn't know, so rewriting or copying
+    /// the data could lose fields. Every write checks this; `rtask migrate`
+    /// checks its source too.
+    fn check_schema(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        Ok(self.load()?.find(id).cloned())
+    }
+
+    fn list(&self, filter: &TaskFilter) -> Result<Vec<Task>, StoreError> {
+        Ok(self
+            .load()?
+            .tasks
+            .into_iter()
+            .filter(|task| filter.matches(task))
+            .collect())
+    }
+
+    /// Adds a task, assigning it the next free ID. Returns the stored task.
+    fn insert(&self, mut task: Task) -> Result<Task, StoreError> {
+        if task.uuid.is_none() {
+            task.uuid = Some(new_uuid());
+        }
+        self.transaction(&mut |data| {
+            task.id = data.allocate_id();
+            data.tasks.push(task.clone());
+            Ok(())
+        })?;
+        Ok(task)
+    }
+
+    /// Replaces the stored task with the same ID.
+    fn update(&self, task: &Task) -> Result<(), StoreError> {
+        self.transaction(&mut |data| {
+            let stored = data.find_mut(task.id).ok_or(StoreError::NotFound(task.id))?;
+            *stored = task.clone();
+            Ok(())
+        })
+    }
+
+    /// Removes a task, returning it if it existed.
+    fn delete(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        let mut removed = None;
+        self.transaction(&mut |data| {
+            removed = data.remove(id);
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+}
+
+impl dyn TaskStore + '_ {
+    /// Like [`TaskStore::transaction`], but lets the body return a value.
+    pub fn modify<T>(
+        &self,
+        f: impl FnOnce(&mut TaskData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut f = Some(f);
+        let mut output = None;
+        self.transaction(&mut |data| {
+            let f = f.take().expect("transaction body runs once");
+            output = Some(f(data)?);
+            Ok(())
+        })?;
+        Ok(output.expect("transaction body ran"))
+    }
+}
+
+/// Opens the store configured in `AppConfig`.
+pub fn open_store() -> Result<Box<dyn TaskStore>, StoreError> {
+    Ok(Box::new(open_journaled()?))
+}
+
+/// The configured store with every change recorded in `journal.json` next
+/// to it, for `rtask undo` and `rtask redo`, and backed up first. Changes
+/// also go to the event history in `history.jsonl`, unless the backend is
+/// the event log itself.
+pub fn open_journaled() -> Result<JournaledStore, StoreError> {
+    let config = load_config();
+    let mut backend = open_backend(config.backend)?;
+    if config.backend != StorageBackend::Events {
+        backend = Box::new(EventLoggedStore::new(backend, get_history_file()));
+    }
+    let backed_up = BackedUpStore::new(backend, configured_backups());
+    Ok(JournaledStore::new(Box::new(backed_up), get_journal_file()))
+}
+
+/// The rolling backups `AppConfig` asks for, in `<tasks dir>/backups`.
+pub fn configured_backups() -> Backups {
+    let config = load_config();
+    Backups::new(get_backups_dir(), config.backups, config.daily_backups)
+}
+
+pub fn open_backend(backend: StorageBackend) -> Result<Box<dyn TaskStore>, StoreError> {
+    open_backend_in(backend, &get_store_dir())
+}
+
+/// Opens the store kept in `dir` with `backend`.
+pub fn open_backend_in(backend: StorageBackend, dir: &Path) -> Result<Box<dyn TaskStore>, StoreError> {
+    match backend {
+        StorageBackend::Json => Ok(Box::new(JsonStore::new(dir.join("tasks.json")))),
+        StorageBackend::Events => Ok(Box::new(EventStore::new(dir.join("events.jsonl")))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Ok(Box::new(SqliteStore::open(dir.join("tasks.db"))?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Err(StoreError::Backend(
+            "this build of rtask has no SQLite support; reinstall it with `--features sqlite`"
+                .to_string(),
+        )),
+    }
+}
+
+/// The files `backend` keeps a store in, inside `dir`.
+pub fn backend_files(backend: StorageBackend, dir: &Path) -> Vec<PathBuf> {
+    let names: &[&str] = match backend {
+        StorageBackend::Json => &["tasks.json"],
+        StorageBackend::Events => &["events.jsonl", "events.snapshot.json"],
+        StorageBackend::Sqlite => &["tasks.db", "tasks.db-wal", "tasks.db-shm"],
+    };
+    names.iter().map(|name| dir.join(name)).collect()
+}
+
+/// Opens list `name` for reading, e.g. for views across every list.
+/// Changes should go through [`open_store`] with the list made active.
+pub fn open_list(name: &str) -> Result<Box<dyn TaskStore>, StoreError> {
+    open_backend_in(load_config().backend, &get_list_dir(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Annotation;
+
+    fn parse(terms: &[&str]) -> TagFilter {
+        TagFilter::parse(&terms.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_each_term_form() {
+        let filter = parse(&["backend", "+tag:api,web", "-tag:someday", "!wip", " , "]);
+        assert_eq!(
+            filter.all_of,
+            vec![vec!["backend".to_string()], vec!["api".to_string(), "web".to_string()]]
+        );
+        assert_eq!(filter.none_of, vec!["someday".to_string(), "wip".to_string()]);
+        assert!(parse(&[""]).is_empty());
+    }
+
+    #[test]
+    fn needs_every_group_and_none_of_the_excluded() {
+        let filter = parse(&["backend", "api,web", "-someday"]);
+        assert!(filter.matches(&tags(&["backend", "web"])));
+        assert!(!filter.matches(&tags(&["backend"])));
+        assert!(!filter.matches(&tags(&["backend", "api", "someday"])));
+        assert!(TagFilter::default().matches(&[]));
+    }
+
+    #[test]
+    fn search_looks_at_descriptions_and_annotations() {
+        let mut task = crate::model::tests::task(1, "Launch");
+        task.description = Some("See the **Design** doc".to_string());
+        task.annotations.push(Annotation {
+            created_at: "2100-01-01 09:00".to_string(),
+            text: "waiting on review".to_string(),
+        });
+        let search = |text: &str| TaskFilter { search: Some(text.to_string()), ..TaskFilter::default() };
+
+        for text in ["launch", "design", "REVIEW"] {
+            assert!(search(text).matches(&task), "{}", text);
+        }
+        assert!(!search("budget").matches(&task));
+    }
+
+    #[test]
+    fn a_trailing_star_matches_a_prefix() {
+        assert!(TagFilter::tag_matches("work/api", "work/*"));
+        assert!(!TagFilter::tag_matches("home", "work*"));
+        assert!(!TagFilter::tag_matches("worker", "work"));
+        assert!(parse(&["-wo*"]).matches(&tags(&["home"])));
+        assert!(!parse(&["-wo*"]).matches(&tags(&["work"])));
+    }
+}
@@ -0,0 +1,209 @@
+//! Versioning of the `tasks.json` layout.
+//!
+//! The file is an envelope `{ "schema_version": N, "next_id": .., "tasks": [..] }`.
+//! Older layouts are upgraded one step at a time by [`MIGRATIONS`] when
+//! they are read; nothing on disk changes until the next save.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::StoreError;
+use crate::model::TaskData;
+
+/// Upgrade steps; `MIGRATIONS[n]` turns a version `n` document into
+/// version `n + 1`. Append a step here whenever the on-disk shape changes.
+const MIGRATIONS: &[fn(Value) -> Value] = &[
+    // v0: a bare array of tasks, before IDs were permanent.
+    |tasks| json!({ "next_id": 1, "tasks": tasks }),
+    // v1: `{ next_id, tasks }` without a version marker.
+    |mut doc| {
+        doc["schema_version"] = json!(2);
+        doc
+    },
+    // v2: no recurring series.
+    |mut doc| {
+        doc["series"] = json!([]);
+        doc["schema_version"] = json!(3);
+        doc
+    },
+    // v3: flat subtasks without IDs. The fields default on load and
+    // `TaskData::normalize` numbers the nodes.
+    |mut doc| {
+        doc["schema_version"] = json!(4);
+        doc
+    },
+    // v4: no task descriptions or annotations.
+    |doc| {
+        let doc = add_field(doc, "tasks", "description", Value::Null);
+        let doc = add_field(doc, "series", "description", Value::Null);
+        let mut doc = add_field(doc, "tasks", "annotations", json!([]));
+        doc["schema_version"] = json!(5);
+        doc
+    },
+    // v5: no dependencies between tasks.
+    |doc| {
+        let mut doc = add_field(doc, "tasks", "depends_on", json!([]));
+        doc["schema_version"] = json!(6);
+        doc
+    },
+    // v6: no links from a promoted subtask to the task it came from.
+    |doc| {
+        let mut doc = add_field(doc, "tasks", "parent", Value::Null);
+        doc["schema_version"] = json!(7);
+        doc
+    },
+    // v7: no saved state for reopening a completed task.
+    |doc| {
+        let mut doc = add_field(doc, "tasks", "reopen", Value::Null);
+        doc["schema_version"] = json!(8);
+        doc
+    },
+    // v8: no projects.
+    |doc| {
+        let doc = add_field(doc, "tasks", "project", Value::Null);
+        let mut doc = add_field(doc, "series", "project", Value::Null);
+        doc["schema_version"] = json!(9);
+        doc
+    },
+];
+
+/// Gives every entry of the `list` array a `field` set to `value`, unless
+/// it already has one.
+fn add_field(mut doc: Value, list: &str, field: &str, value: Value) -> Value {
+    if let Some(entries) = doc.get_mut(list).and_then(Value::as_array_mut) {
+        for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+            entry.entry(field).or_insert_with(|| value.clone());
+        }
+    }
+    doc
+}
+
+pub const CURRENT_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Refuses a document written with a newer schema than this build knows;
+/// rewriting it would drop whatever the newer version added.
+pub fn check_supported(version: u32) -> Result<(), StoreError> {
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(StoreError::NewerSchema {
+            found: version,
+            supported: CURRENT_SCHEMA_VERSION,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    data: &'a TaskData,
+}
+
+/// Works out which layout `contents` uses from the raw JSON, without
+/// building the tasks, so a file from a newer schema that this build can't
+/// parse is still recognised as one.
+pub fn detect_version(contents: &str) -> Result<u32, serde_json::Error> {
+    let doc: Value = serde_json::from_str(contents)?;
+    if doc.is_array() {
+        return Ok(0);
+    }
+    match doc.get("schema_version") {
+        None | Some(Value::Null) => Ok(1),
+        Some(version) => u32::deserialize(version),
+    }
+}
+
+/// Runs every upgrade step from `version` up to the current version.
+pub fn migrate(mut doc: Value, version: u32) -> Value {
+    for step in MIGRATIONS.iter().skip(version as usize) {
+        doc = step(doc);
+    }
+    doc
+}
+
+pub fn to_json(data: &TaskData) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Envelope {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::json::parse_task_data;
+
+    #[test]
+    fn detects_each_layout() {
+        assert_eq!(detect_version("[]").unwrap(), 0);
+        assert_eq!(detect_version(r#"{"next_id": 1, "tasks": []}"#).unwrap(), 1);
+        assert_eq!(detect_version(r#"{"schema_version": 3, "next_id": 1, "tasks": []}"#).unwrap(), 3);
+    }
+
+    #[test]
+    fn upgrades_a_bare_task_array() {
+        let legacy = r#"[{
+            "id": 7, "name": "old", "priority": "high", "status": "pending",
+            "progress": 0, "due_date": null, "tags": [], "created_at": "2024-01-01 09:00",
+            "completed_at": null,
+            "subtasks": [{"name": "a", "completed": true}, {"name": "b", "completed": false}]
+        }]"#;
+        let (data, version) = parse_task_data(legacy).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(data.next_id, 8);
+        assert!(data.series.is_empty());
+        let task = &data.tasks[0];
+        assert!(task.uuid.is_some());
+        let ids: Vec<usize> = task.subtasks.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn every_step_ends_at_the_current_version() {
+        let doc = migrate(json!([]), 0);
+        assert_eq!(doc["schema_version"], json!(CURRENT_SCHEMA_VERSION));
+        assert_eq!(doc["series"], json!([]));
+        let doc = migrate(json!({"next_id": 4, "tasks": []}), 1);
+        assert_eq!(doc["schema_version"], json!(CURRENT_SCHEMA_VERSION));
+        assert_eq!(doc["next_id"], json!(4));
+    }
+
+    #[test]
+    fn fills_in_fields_added_since_version_4() {
+        let doc = json!({
+            "schema_version": 4,
+            "next_id": 3,
+            "tasks": [{"id": 1, "name": "old"}, {"id": 2, "name": "new", "project": "acme"}],
+            "series": [{"uuid": "s", "name": "weekly"}],
+        });
+        let doc = migrate(doc, 4);
+        assert_eq!(doc["schema_version"], json!(9));
+        let old = &doc["tasks"][0];
+        for field in ["description", "parent", "reopen", "project"] {
+            assert_eq!(old[field], Value::Null, "{}", field);
+        }
+        assert_eq!(old["annotations"], json!([]));
+        assert_eq!(old["depends_on"], json!([]));
+        assert_eq!(doc["tasks"][1]["project"], json!("acme"));
+        assert_eq!(doc["series"][0]["project"], Value::Null);
+    }
+
+    #[test]
+    fn reads_the_version_of_a_file_it_cannot_parse() {
+        let newer = r#"{"schema_version": 99, "tasks": {"format": "something new"}}"#;
+        assert_eq!(detect_version(newer).unwrap(), 99);
+        assert!(parse_task_data(newer).is_err());
+        assert!(detect_version(r#"{"schema_version": "two"}"#).is_err());
+    }
+
+    #[test]
+    fn writes_the_current_version_and_refuses_newer_ones() {
+        let json = to_json(&TaskData::default()).unwrap();
+        assert_eq!(detect_version(&json).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert!(check_supported(CURRENT_SCHEMA_VERSION).is_ok());
+        assert!(matches!(
+            check_supported(CURRENT_SCHEMA_VERSION + 1),
+            Err(StoreError::NewerSchema { .. })
+        ));
+    }
+}
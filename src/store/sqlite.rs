@@ -0,0 +1,638 @@
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::json::LOCK_TIMEOUT;
+use super::{StoreError, TaskFilter, TaskStore};
+use crate::model::{Annotation, Priority, Series, SubTask, Task, TaskData, new_uuid};
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Backend(format!("SQLite error: {}", e))
+    }
+}
+
+/// Schema upgrades, applied in order. `PRAGMA user_version` records how
+/// many have run, so new ones must only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE tasks (
+        id INTEGER PRIMARY KEY,
+        uuid TEXT,
+        name TEXT NOT NULL,
+        priority TEXT NOT NULL,
+        status TEXT NOT NULL,
+        progress INTEGER NOT NULL,
+        due_date TEXT,
+        created_at TEXT NOT NULL,
+        completed_at TEXT
+    );
+    CREATE TABLE task_tags (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (task_id, position)
+    );
+    CREATE TABLE subtasks (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        PRIMARY KEY (task_id, position)
+    );
+    CREATE INDEX idx_tasks_status ON tasks(status);
+    CREATE INDEX idx_tasks_due_date ON tasks(due_date);
+    CREATE INDEX idx_task_tags_tag ON task_tags(tag);",
+    "ALTER TABLE tasks ADD COLUMN description TEXT;",
+    "CREATE TABLE annotations (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        text TEXT NOT NULL,
+        PRIMARY KEY (task_id, position)
+    );",
+    "ALTER TABLE tasks ADD COLUMN series TEXT;
+    CREATE TABLE series (
+        uuid TEXT PRIMARY KEY,
+        template TEXT NOT NULL
+    );",
+    "CREATE TABLE task_dependencies (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        depends_on INTEGER NOT NULL
+            REFERENCES tasks(id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+        PRIMARY KEY (task_id, depends_on)
+    );",
+    // Subtask trees: rows stay in depth-first order by `position`, and
+    // `parent_id` names the enclosing subtask (NULL at the top level).
+    "ALTER TABLE subtasks ADD COLUMN subtask_id INTEGER;
+    ALTER TABLE subtasks ADD COLUMN parent_id INTEGER;
+    ALTER TABLE subtasks ADD COLUMN priority TEXT;
+    ALTER TABLE subtasks ADD COLUMN due_date TEXT;",
+    "ALTER TABLE tasks ADD COLUMN parent_id INTEGER;",
+    // JSON-encoded `ReopenState`.
+    "ALTER TABLE tasks ADD COLUMN reopen_state TEXT;",
+    "ALTER TABLE tasks ADD COLUMN project TEXT;
+    CREATE INDEX idx_tasks_project ON tasks(project);",
+    // A prerequisite may be archived and so no longer in `tasks`; keep the
+    // dependency instead of cascading it away.
+    "CREATE TABLE task_dependencies_new (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        depends_on INTEGER NOT NULL,
+        PRIMARY KEY (task_id, depends_on)
+    );
+    INSERT INTO task_dependencies_new SELECT task_id, depends_on FROM task_dependencies;
+    DROP TABLE task_dependencies;
+    ALTER TABLE task_dependencies_new RENAME TO task_dependencies;",
+];
+
+const TASK_COLUMNS: &str =
+    "id, uuid, name, priority, status, progress, due_date, created_at, completed_at, description, series, parent_id, reopen_state, project";
+
+/// Tasks in a SQLite database, with tags and subtasks in their own tables
+/// so filters can run as indexed queries instead of loading every task.
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let conn = Connection::open(&path)?;
+        conn.busy_timeout(LOCK_TIMEOUT)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let store = Self { path, conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn migrate(&self) -> Result<(), StoreError> {
+        let version: usize = self.conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.begin()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", idx + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Starts a write transaction. `IMMEDIATE` takes SQLite's write lock up
+    /// front, so concurrent rtask processes queue up behind the busy
+    /// timeout instead of failing halfway through.
+    fn begin(&self) -> Result<Transaction<'_>, StoreError> {
+        self.check_schema()?;
+        Ok(Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?)
+    }
+
+    fn query_tasks(
+        conn: &Connection,
+        where_clause: &str,
+        params: &[String],
+    ) -> Result<Vec<Task>, StoreError> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE {} ORDER BY id",
+            TASK_COLUMNS, where_clause
+        ))?;
+        let mut tasks = stmt
+            .query_map(params_from_iter(params), task_from_row)?
+            .collect::<Result<Vec<Task>, _>>()?;
+
+        let index: HashMap<usize, usize> = tasks
+            .iter()
+            .enumerate()
+            .map(|(idx, task)| (task.id, idx))
+            .collect();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT task_id, tag FROM task_tags
+             WHERE task_id IN (SELECT id FROM tasks WHERE {})
+             ORDER BY task_id, position",
+            where_clause
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            let task_id: usize = row.get(0)?;
+            if let Some(&idx) = index.get(&task_id) {
+                tasks[idx].tags.push(row.get(1)?);
+            }
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT task_id, subtask_id, parent_id, name, completed, priority, due_date FROM subtasks
+             WHERE task_id IN (SELECT id FROM tasks WHERE {})
+             ORDER BY task_id, position",
+            where_clause
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            let task_id: usize = row.get(0)?;
+            let Some(&idx) = index.get(&task_id) else {
+                continue;
+            };
+            let subtask = SubTask {
+                id: row.get::<_, Option<usize>>(1)?.unwrap_or(0),
+                name: row.get(3)?,
+                completed: row.get(4)?,
+                priority: row.get::<_, Option<String>>(5)?.map(Priority::from),
+                due_date: row.get(6)?,
+                subtasks: Vec::new(),
+            };
+            let parent_id: Option<usize> = row.get(2)?;
+            match parent_id.and_then(|id| find_subtask_mut(&mut tasks[idx].subtasks, id)) {
+                Some(parent) => parent.subtasks.push(subtask),
+                None => tasks[idx].subtasks.push(subtask),
+            }
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT task_id, depends_on FROM task_dependencies
+             WHERE task_id IN (SELECT id FROM tasks WHERE {})
+             ORDER BY task_id, rowid",
+            where_clause
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            let task_id: usize = row.get(0)?;
+            if let Some(&idx) = index.get(&task_id) {
+                tasks[idx].depends_on.push(row.get(1)?);
+            }
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT task_id, created_at, text FROM annotations
+             WHERE task_id IN (SELECT id FROM tasks WHERE {})
+             ORDER BY task_id, position",
+            where_clause
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            let task_id: usize = row.get(0)?;
+            if let Some(&idx) = index.get(&task_id) {
+                tasks[idx].annotations.push(Annotation {
+                    created_at: row.get(1)?,
+                    text: row.get(2)?,
+                });
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    fn read_next_id(conn: &Connection) -> Result<usize, StoreError> {
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'next_id'", [], |row| row.get(0))
+            .optional()?;
+        Ok(value.and_then(|v| v.parse().ok()).unwrap_or(1))
+    }
+
+    fn write_next_id(conn: &Connection, next_id: usize) -> Result<(), StoreError> {
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES ('next_id', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![next_id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Series templates are only ever read and written as a whole, so each
+    /// one is kept as a JSON document.
+    fn read_series(conn: &Connection) -> Result<Vec<Series>, StoreError> {
+        let mut stmt = conn.prepare("SELECT template FROM series ORDER BY rowid")?;
+        let templates = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        templates
+            .iter()
+            .map(|t| serde_json::from_str(t).map_err(StoreError::from))
+            .collect()
+    }
+
+    fn write_series(conn: &Connection, series: &[Series]) -> Result<(), StoreError> {
+        conn.execute("DELETE FROM series", [])?;
+        for template in series {
+            conn.execute(
+                "INSERT INTO series (uuid, template) VALUES (?1, ?2)",
+                params![template.uuid, serde_json::to_string(template)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn load_data(conn: &Connection) -> Result<TaskData, StoreError> {
+        let mut data = TaskData {
+            next_id: Self::read_next_id(conn)?,
+            tasks: Self::query_tasks(conn, "1 = 1", &[])?,
+            series: Self::read_series(conn)?,
+        };
+        data.normalize();
+        Ok(data)
+    }
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        uuid: row.get(1)?,
+        name: row.get(2)?,
+        priority: Priority::from(row.get::<_, String>(3)?),
+        status: parse_column(row, 4)?,
+        progress: row.get(5)?,
+        due_date: row.get(6)?,
+        tags: Vec::new(),
+        created_at: row.get(7)?,
+        completed_at: row.get(8)?,
+        subtasks: Vec::new(),
+        description: row.get(9)?,
+        annotations: Vec::new(),
+        series: row.get(10)?,
+        depends_on: Vec::new(),
+        parent: row.get(11)?,
+        reopen: json_column(row, 12)?,
+        project: row.get(13)?,
+    })
+}
+
+/// SQL matching a `task_tags.tag` against any of `patterns`, as
+/// [`TagFilter::tag_matches`](super::TagFilter::tag_matches) does. Adds
+/// the patterns to `params`.
+fn tag_condition(patterns: &[String], params: &mut Vec<String>) -> String {
+    let mut alternatives = Vec::new();
+    for pattern in patterns {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => {
+                params.push(prefix.to_string());
+                let n = params.len();
+                alternatives.push(format!("substr(tag, 1, length(?{n})) = ?{n}"));
+            }
+            None => {
+                params.push(pattern.clone());
+                alternatives.push(format!("tag = ?{}", params.len()));
+            }
+        }
+    }
+    alternatives.join(" OR ")
+}
+
+/// Reads a text column holding one of the model's string-encoded enums.
+fn parse_column<T: FromStr<Err = String>>(row: &Row, idx: usize) -> rusqlite::Result<T> {
+    let value: String = row.get(idx)?;
+    value.parse().map_err(|e: String| {
+        rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, e.into())
+    })
+}
+
+/// Reads an optional text column holding a JSON-encoded value.
+fn json_column<T: DeserializeOwned>(row: &Row, idx: usize) -> rusqlite::Result<Option<T>> {
+    let value: Option<String> = row.get(idx)?;
+    value
+        .map(|v| serde_json::from_str(&v))
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, e.into()))
+}
+
+/// Inserts or fully replaces a task row together with its tags and
+/// subtasks.
+fn write_task(conn: &Connection, task: &Task) -> Result<(), StoreError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO tasks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+             ON CONFLICT(id) DO UPDATE SET
+                uuid = excluded.uuid,
+                name = excluded.name,
+                priority = excluded.priority,
+                status = excluded.status,
+                progress = excluded.progress,
+                due_date = excluded.due_date,
+                created_at = excluded.created_at,
+                completed_at = excluded.completed_at,
+                description = excluded.description,
+                series = excluded.series,
+                parent_id = excluded.parent_id,
+                reopen_state = excluded.reopen_state,
+                project = excluded.project",
+            TASK_COLUMNS
+        ),
+        params![
+            task.id,
+            task.uuid,
+            task.name,
+            task.priority.as_str(),
+            task.status.as_str(),
+            task.progress,
+            task.due_date,
+            task.created_at,
+            task.completed_at,
+            task.description,
+            task.series,
+            task.parent,
+            task.reopen.as_ref().map(serde_json::to_string).transpose()?,
+            task.project,
+        ],
+    )?;
+
+    conn.execute("DELETE FROM task_tags WHERE task_id = ?1", params![task.id])?;
+    for (position, tag) in task.tags.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO task_tags (task_id, position, tag) VALUES (?1, ?2, ?3)",
+            params![task.id, position, tag],
+        )?;
+    }
+
+    conn.execute("DELETE FROM subtasks WHERE task_id = ?1", params![task.id])?;
+    let mut position = 0;
+    write_subtasks(conn, task.id, None, &task.subtasks, &mut position)?;
+
+    conn.execute("DELETE FROM task_dependencies WHERE task_id = ?1", params![task.id])?;
+    for dependency in &task.depends_on {
+        conn.execute(
+            "INSERT OR IGNORE INTO task_dependencies (task_id, depends_on) VALUES (?1, ?2)",
+            params![task.id, dependency],
+        )?;
+    }
+
+    conn.execute("DELETE FROM annotations WHERE task_id = ?1", params![task.id])?;
+    for (position, annotation) in task.annotations.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO annotations (task_id, position, created_at, text) VALUES (?1, ?2, ?3, ?4)",
+            params![task.id, position, annotation.created_at, annotation.text],
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes a subtask tree depth-first, so reading the rows back in
+/// `position` order always meets a parent before its children.
+fn write_subtasks(
+    conn: &Connection,
+    task_id: usize,
+    parent_id: Option<usize>,
+    subtasks: &[SubTask],
+    position: &mut usize,
+) -> Result<(), StoreError> {
+    for subtask in subtasks {
+        conn.execute(
+            "INSERT INTO subtasks
+                (task_id, position, subtask_id, parent_id, name, completed, priority, due_date)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                task_id,
+                *position,
+                subtask.id,
+                parent_id,
+                subtask.name,
+                subtask.completed,
+                subtask.priority.as_ref().map(|p| p.as_str()),
+                subtask.due_date,
+            ],
+        )?;
+        *position += 1;
+        write_subtasks(conn, task_id, Some(subtask.id), &subtask.subtasks, position)?;
+    }
+    Ok(())
+}
+
+fn find_subtask_mut(nodes: &mut [SubTask], id: usize) -> Option<&mut SubTask> {
+    for node in nodes {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find_subtask_mut(&mut node.subtasks, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn delete_task(conn: &Connection, id: usize) -> Result<bool, StoreError> {
+    Ok(conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])? > 0)
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&self) -> Result<TaskData, StoreError> {
+        Self::load_data(&self.conn)
+    }
+
+    /// The schema version is SQLite's `user_version`, one per migration.
+    fn check_schema(&self) -> Result<(), StoreError> {
+        let version: usize = self.conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(StoreError::NewerSchema {
+                found: version as u32,
+                supported: MIGRATIONS.len() as u32,
+            });
+        }
+        Ok(())
+    }
+
+    /// Loads everything inside one write transaction and writes back only
+    /// the tasks the body actually changed.
+    fn transaction(
+        &self,
+        f: &mut dyn FnMut(&mut TaskData) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let tx = self.begin()?;
+        let before = Self::load_data(&tx)?;
+        let mut data = before.clone();
+
+        f(&mut data)?;
+
+        let mut previous: HashMap<usize, serde_json::Value> = HashMap::new();
+        for task in &before.tasks {
+            previous.insert(task.id, serde_json::to_value(task)?);
+        }
+
+        for task in &data.tasks {
+            let changed = match previous.remove(&task.id) {
+                Some(old) => old != serde_json::to_value(task)?,
+                None => true,
+            };
+            if changed {
+                write_task(&tx, task)?;
+            }
+        }
+        for removed_id in previous.keys() {
+            delete_task(&tx, *removed_id)?;
+        }
+        if data.next_id != before.next_id {
+            Self::write_next_id(&tx, data.next_id)?;
+        }
+        if data.series != before.series {
+            Self::write_series(&tx, &data.series)?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        Ok(Self::query_tasks(&self.conn, "id = ?1", &[id.to_string()])?
+            .into_iter()
+            .next())
+    }
+
+    fn list(&self, filter: &TaskFilter) -> Result<Vec<Task>, StoreError> {
+        let mut conditions = vec!["1 = 1".to_string()];
+        let mut params = Vec::new();
+
+        match filter.closed {
+            Some(true) => conditions.push("status IN ('done', 'cancelled')".to_string()),
+            Some(false) => conditions.push("status NOT IN ('done', 'cancelled')".to_string()),
+            None => {}
+        }
+        if let Some(status) = filter.status {
+            params.push(status.as_str().to_string());
+            conditions.push(format!("status = ?{}", params.len()));
+        }
+        for group in &filter.tags.all_of {
+            let matches = tag_condition(group, &mut params);
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM task_tags WHERE task_id = tasks.id AND ({}))",
+                matches
+            ));
+        }
+        if !filter.tags.none_of.is_empty() {
+            let matches = tag_condition(&filter.tags.none_of, &mut params);
+            conditions.push(format!(
+                "NOT EXISTS (SELECT 1 FROM task_tags WHERE task_id = tasks.id AND ({}))",
+                matches
+            ));
+        }
+        if let Some(project) = &filter.project {
+            params.push(project.clone());
+            let n = params.len();
+            conditions.push(format!(
+                "(project = ?{n} OR substr(project, 1, length(?{n}) + 1) = ?{n} || '.')"
+            ));
+        }
+        if let Some(priorities) = &filter.priorities {
+            let mut placeholders = Vec::new();
+            for priority in priorities {
+                params.push(priority.as_str().to_string());
+                placeholders.push(format!("?{}", params.len()));
+            }
+            conditions.push(format!(
+                "priority COLLATE NOCASE IN ({})",
+                if placeholders.is_empty() { "NULL".to_string() } else { placeholders.join(", ") }
+            ));
+        }
+        if let Some(date) = &filter.due_on {
+            params.push(date.clone());
+            conditions.push(format!("due_date = ?{}", params.len()));
+        }
+        if let Some(text) = &filter.search {
+            params.push(text.to_lowercase());
+            let n = params.len();
+            conditions.push(format!(
+                "(instr(lower(name), ?{n}) > 0
+                  OR instr(lower(coalesce(description, '')), ?{n}) > 0
+                  OR EXISTS (SELECT 1 FROM annotations
+                             WHERE task_id = tasks.id AND instr(lower(text), ?{n}) > 0))"
+            ));
+        }
+
+        Self::query_tasks(&self.conn, &conditions.join(" AND "), &params)
+    }
+
+    fn insert(&self, mut task: Task) -> Result<Task, StoreError> {
+        if task.uuid.is_none() {
+            task.uuid = Some(new_uuid());
+        }
+
+        let tx = self.begin()?;
+        let max_id: Option<usize> = tx.query_row("SELECT MAX(id) FROM tasks", [], |row| row.get(0))?;
+        task.id = Self::read_next_id(&tx)?.max(max_id.unwrap_or(0) + 1);
+        write_task(&tx, &task)?;
+        Self::write_next_id(&tx, task.id + 1)?;
+        tx.commit()?;
+        Ok(task)
+    }
+
+    fn update(&self, task: &Task) -> Result<(), StoreError> {
+        let tx = self.begin()?;
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM tasks WHERE id = ?1)",
+            params![task.id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(StoreError::NotFound(task.id));
+        }
+        write_task(&tx, task)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, id: usize) -> Result<Option<Task>, StoreError> {
+        let tx = self.begin()?;
+        let task = Self::query_tasks(&tx, "id = ?1", &[id.to_string()])?
+            .into_iter()
+            .next();
+        if task.is_some() {
+            delete_task(&tx, id)?;
+            // Unlike archiving, deleting a task drops the links to it.
+            tx.execute("DELETE FROM task_dependencies WHERE depends_on = ?1", params![id])?;
+            tx.execute("UPDATE tasks SET parent_id = NULL WHERE parent_id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(task)
+    }
+}
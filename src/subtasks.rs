@@ -1,185 +1,342 @@
 use colored::*;
 use dialoguer::{Confirm, Input, Select};
-use chrono::Local;
-
-use crate::model::SubTask;
-use crate::commands::{load_tasks, save_tasks};
-
-pub fn add_subtask(id: usize, name: String) {
-    let mut tasks = load_tasks();
-    
-    let task_idx = tasks.iter().position(|t| t.id == id);
-    
-    match task_idx {
-        Some(idx) => {
-            let subtask = SubTask {
-                name,
-                completed: false,
-            };
-            
-            tasks[idx].subtasks.push(subtask);
-            
-            match save_tasks(&tasks) {
-                Ok(_) => {
-                    println!(
-                        "{} {} {}",
-                        "✓ Added subtask to:".green().bold(),
-                        tasks[idx].name.bright_white(),
-                        format!("(#{}/{})", tasks[idx].subtasks.len(), tasks[idx].subtasks.len()).cyan()
-                    );
-                }
-                Err(e) => {
-                    println!("{} {}", "Error adding subtask:".red().bold(), e);
-                }
-            }
+use std::fmt;
+use std::str::FromStr;
+
+use crate::model::{new_uuid, timestamp, Priority, PriorityScale, Status, SubTask, Task, TaskData};
+use crate::commands::{
+    format_priority, load_or_exit, print_completion, print_store_error, settle_subtasks_in, store_or_exit,
+    validate_due_date, Completion,
+};
+use crate::store::StoreError;
+
+/// Addresses a subtask as `<task id>.<position>.<position>...` with
+/// 1-based positions, so `4.2.1` is the first child of the second subtask
+/// of task 4. A bare task ID addresses the task's top level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtaskPath {
+    pub task_id: usize,
+    /// 0-based positions from the top of the tree down.
+    pub indices: Vec<usize>,
+}
+
+impl SubtaskPath {
+    /// The part after the task ID, e.g. `2.1`.
+    pub fn label(&self) -> String {
+        subtask_label(&self.indices)
+    }
+}
+
+/// Formats 0-based positions as a 1-based dotted path.
+pub fn subtask_label(indices: &[usize]) -> String {
+    indices
+        .iter()
+        .map(|i| (i + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl fmt::Display for SubtaskPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.indices.is_empty() {
+            write!(f, "{}", self.task_id)
+        } else {
+            write!(f, "{}.{}", self.task_id, self.label())
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+    }
+}
+
+impl FromStr for SubtaskPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('.');
+        let task_id = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(|| format!("invalid subtask path \"{}\" (expected e.g. 4.2.1)", s))?;
+
+        let indices = parts
+            .map(|p| match p.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n - 1),
+                _ => Err(format!(
+                    "invalid subtask path \"{}\" (positions start at 1, e.g. 4.2.1)",
+                    s
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SubtaskPath { task_id, indices })
+    }
+}
+
+pub fn parse_subtask_path(s: &str) -> Result<SubtaskPath, String> {
+    s.parse()
+}
+
+/// Builds a path from the older `--task-id 4 --subtask-index 2` flags.
+/// The index may itself be a dotted path like `2.1`.
+pub fn path_from_flags(task_id: usize, index: &str) -> Result<SubtaskPath, String> {
+    format!("{}.{}", task_id, index).parse()
+}
+
+fn subtask_not_found(path: &SubtaskPath) -> StoreError {
+    StoreError::Rejected(format!("Subtask {} not found", path))
+}
+
+/// Every node of a task's subtask tree with its position and depth,
+/// parents before their children.
+pub fn flatten_subtasks(task: &Task) -> Vec<(Vec<usize>, &SubTask)> {
+    fn visit<'a>(nodes: &'a [SubTask], prefix: &[usize], out: &mut Vec<(Vec<usize>, &'a SubTask)>) {
+        for (idx, node) in nodes.iter().enumerate() {
+            let mut path = prefix.to_vec();
+            path.push(idx);
+            out.push((path.clone(), node));
+            visit(&node.subtasks, &path, out);
         }
     }
+
+    let mut out = Vec::new();
+    visit(&task.subtasks, &[], &mut out);
+    out
 }
 
-pub fn toggle_subtask(id: usize, subtask_idx: usize) {
-    let mut tasks = load_tasks();
-    
-    let task_idx = tasks.iter().position(|t| t.id == id);
-    
-    match task_idx {
-        Some(idx) => {
-            if subtask_idx >= tasks[idx].subtasks.len() {
-                println!("{}", format!("Subtask {} not found", subtask_idx + 1).red());
-                return;
-            }
-            
-            tasks[idx].subtasks[subtask_idx].completed = !tasks[idx].subtasks[subtask_idx].completed;
-            
-            // Update task progress based on subtasks
-            if !tasks[idx].subtasks.is_empty() {
-                let completed_count = tasks[idx].subtasks.iter().filter(|s| s.completed).count();
-                let progress = (completed_count as f32 / tasks[idx].subtasks.len() as f32 * 100.0) as u8;
-                tasks[idx].progress = progress;
-                
-                if progress == 100 {
-                    tasks[idx].status = "done".to_string();
-                    tasks[idx].completed_at = Some(Local::now().format("%Y-%m-%d %H:%M").to_string());
-                }
-            }
-            
-            match save_tasks(&tasks) {
-                Ok(_) => {
-                    let status = if tasks[idx].subtasks[subtask_idx].completed {
-                        "completed".green()
-                    } else {
-                        "uncompleted".yellow()
-                    };
-                    
-                    println!(
-                        "{} {} {}",
-                        "✓ Subtask:".green().bold(),
-                        tasks[idx].subtasks[subtask_idx].name.bright_white(),
-                        format!("({}) - {}", status, tasks[idx].name).cyan()
-                    );
-                }
-                Err(e) => {
-                    println!("{} {}", "Error updating subtask:".red().bold(), e);
-                }
-            }
+fn format_checkbox(subtask: &SubTask) -> ColoredString {
+    if subtask.completed {
+        "[✓]".green()
+    } else {
+        "[ ]".yellow()
+    }
+}
+
+/// Prints a task's subtasks as an indented tree, labelled with the paths
+/// `rtask sub` commands accept.
+pub fn print_subtask_tree(task: &Task, scale: &PriorityScale) {
+    for (path, subtask) in flatten_subtasks(task) {
+        let indent = "    ".repeat(path.len() - 1);
+
+        let mut details = Vec::new();
+        if let Some(priority) = &subtask.priority {
+            details.push(format_priority(priority, scale));
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+        if let Some(due) = &subtask.due_date {
+            details.push(format!("due {}", due).yellow().to_string());
         }
+        if !subtask.subtasks.is_empty() {
+            details.push(format!("{}%", subtask.progress()).bright_magenta().to_string());
+        }
+
+        println!(
+            "{}{} {}: {}{}",
+            indent,
+            format_checkbox(subtask),
+            subtask_label(&path).cyan(),
+            subtask.name,
+            if details.is_empty() { String::new() } else { format!(" ({})", details.join(", ")) }
+        );
     }
 }
 
-pub fn remove_subtask(id: usize, subtask_idx: usize) {
-    let mut tasks = load_tasks();
-    
-    let task_idx = tasks.iter().position(|t| t.id == id);
-    
-    match task_idx {
-        Some(idx) => {
-            if subtask_idx >= tasks[idx].subtasks.len() {
-                println!("{}", format!("Subtask {} not found", subtask_idx + 1).red());
-                return;
-            }
-            
-            let subtask_name = tasks[idx].subtasks[subtask_idx].name.clone();
-            
-            if Confirm::new()
-                .with_prompt(format!("Remove subtask \"{}\"?", subtask_name))
-                .default(false)
-                .interact()
-                .unwrap_or(false)
-            {
-                tasks[idx].subtasks.remove(subtask_idx);
-                
-                // Update task progress based on remaining subtasks
-                if !tasks[idx].subtasks.is_empty() {
-                    let completed_count = tasks[idx].subtasks.iter().filter(|s| s.completed).count();
-                    let progress = (completed_count as f32 / tasks[idx].subtasks.len() as f32 * 100.0) as u8;
-                    tasks[idx].progress = progress;
-                }
-                
-                match save_tasks(&tasks) {
-                    Ok(_) => {
-                        println!(
-                            "{} {}",
-                            "✓ Removed subtask:".green().bold(),
-                            subtask_name.bright_white()
-                        );
-                    }
-                    Err(e) => {
-                        println!("{} {}", "Error removing subtask:".red().bold(), e);
-                    }
-                }
+/// Adds a subtask under `parent`: the task's top level for a bare task ID,
+/// otherwise as the last child of the subtask at that path.
+pub fn add_subtask(parent: SubtaskPath, name: String, priority: Option<Priority>, due: Option<String>) {
+    let due_date = match due.as_deref().map(validate_due_date).transpose() {
+        Ok(due) => due,
+        Err(e) => {
+            println!("{}", e.red().bold());
+            return;
+        }
+    };
+
+    let store = store_or_exit();
+    let id = parent.task_id;
+
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        let before = task.reopen_state();
+        let subtask = SubTask {
+            id: task.next_subtask_id(),
+            name,
+            priority,
+            due_date,
+            ..SubTask::default()
+        };
+
+        let siblings = task
+            .subtask_children_mut(&parent.indices)
+            .ok_or_else(|| subtask_not_found(&parent))?;
+        siblings.push(subtask);
+
+        let mut path = parent.clone();
+        path.indices.push(siblings.len() - 1);
+        let name = task.name.clone();
+        settle_subtasks_in(data, id, before)?;
+        Ok((name, path))
+    });
+
+    match result {
+        Ok((task_name, path)) => {
+            println!(
+                "{} {} {}",
+                "✓ Added subtask to:".green().bold(),
+                task_name.bright_white(),
+                format!("({})", path).cyan()
+            );
+        }
+        Err(e) => print_store_error("Error adding subtask:", &e),
+    }
+}
+
+/// Flips a subtask. Checking or unchecking a parent does the same to
+/// everything below it; parents above follow their children.
+pub fn toggle_subtask(path: SubtaskPath) {
+    let store = store_or_exit();
+    let id = path.task_id;
+
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        let before = task.reopen_state();
+        let was = task.status;
+        let subtask = task.subtask_mut(&path.indices).ok_or_else(|| subtask_not_found(&path))?;
+
+        let completed = !subtask.completed;
+        subtask.set_completed(completed);
+        let subtask_name = subtask.name.clone();
+        let task_name = task.name.clone();
+
+        let completion = settle_subtasks_in(data, id, before)?;
+        let status = data.find(id).ok_or(StoreError::NotFound(id))?.status;
+        let now = (status != was).then_some(status);
+        Ok((task_name, subtask_name, completed, now, completion))
+    });
+
+    match result {
+        Ok((task_name, subtask_name, completed, status_change, completion)) => {
+            let status = if completed {
+                "completed".green()
+            } else {
+                "uncompleted".yellow()
+            };
+
+            println!(
+                "{} {} {}",
+                "✓ Subtask:".green().bold(),
+                subtask_name.bright_white(),
+                format!("({}) - {}", status, task_name).cyan()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
+            } else if let Some(status) = status_change {
+                println!("{}", format!("Task is now {}", status).cyan());
             }
         }
-        None => {
+        Err(e) => print_store_error("Error updating subtask:", &e),
+    }
+}
+
+pub fn remove_subtask(path: SubtaskPath) {
+    let store = store_or_exit();
+    let id = path.task_id;
+
+    let task = match store.get(id) {
+        Ok(Some(task)) => task,
+        Ok(None) => {
             println!("{}", format!("Task with ID {} not found", id).red());
+            return;
+        }
+        Err(e) => {
+            println!("{} {}", "Error removing subtask:".red().bold(), e);
+            return;
         }
+    };
+
+    let (subtask_id, subtask_name, children) = match task.subtask(&path.indices) {
+        Some(subtask) => (subtask.id, subtask.name.clone(), subtask.walk().len() - 1),
+        None => {
+            println!("{}", format!("Subtask {} not found", path).red());
+            return;
+        }
+    };
+
+    let prompt = if children > 0 {
+        format!("Remove subtask \"{}\" and its {} nested subtasks?", subtask_name, children)
+    } else {
+        format!("Remove subtask \"{}\"?", subtask_name)
+    };
+
+    if !Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let result = store.modify(|data| {
+        let task = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+        if task.subtask(&path.indices).map(|s| s.id) != Some(subtask_id) {
+            return Err(StoreError::Rejected(
+                "Subtasks changed while confirming; nothing was removed".to_string(),
+            ));
+        }
+        let before = task.reopen_state();
+        task.remove_subtask(&path.indices);
+        settle_subtasks_in(data, id, before)
+    });
+
+    match result {
+        Ok(completion) => {
+            println!(
+                "{} {}",
+                "✓ Removed subtask:".green().bold(),
+                subtask_name.bright_white()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
+            }
+        }
+        Err(e) => print_store_error("Error removing subtask:", &e),
     }
 }
 
 pub fn interactive_manage_subtasks() {
-    let tasks = load_tasks();
-    
+    let tasks = load_or_exit().tasks;
+
     if tasks.is_empty() {
         println!("{}", "No tasks found".yellow());
         return;
     }
-    
+
     let task_names: Vec<String> = tasks
         .iter()
         .map(|t| {
-            let subtask_count = t.subtasks.len();
-            let completed = t.subtasks.iter().filter(|s| s.completed).count();
-            
-            if subtask_count > 0 {
-                format!("[{}] {} ({}/{} subtasks)", t.id, t.name, completed, subtask_count)
+            let subtasks = t.all_subtasks();
+            let completed = subtasks.iter().filter(|s| s.completed).count();
+
+            if !subtasks.is_empty() {
+                format!("[{}] {} ({}/{} subtasks)", t.id, t.name, completed, subtasks.len())
             } else {
                 format!("[{}] {}", t.id, t.name)
             }
         })
         .collect();
-    
+
     let selection = Select::new()
         .with_prompt("Select task to manage subtasks")
         .items(&task_names)
         .interact();
-    
-    match selection {
-        Ok(idx) => {
-            let task_id = tasks[idx].id;
-            interactive_subtask_menu(task_id);
-        }
-        Err(_) => {}
+
+    if let Ok(idx) = selection {
+        let task_id = tasks[idx].id;
+        interactive_subtask_menu(task_id);
     }
 }
 
 pub fn interactive_subtask_menu(id: usize) {
     loop {
-        let tasks = load_tasks();
-        let task = match tasks.iter().find(|t| t.id == id) {
+        let data = load_or_exit();
+        let task = match data.find(id) {
             Some(t) => t,
             None => {
                 println!("{}", format!("Task with ID {} not found", id).red());
@@ -188,40 +345,108 @@ pub fn interactive_subtask_menu(id: usize) {
         };
 
         println!("\n{} {}", "Subtasks for:".cyan().bold(), task.name.bright_white());
-        
+        print_subtask_tree(task, &PriorityScale::load());
+
         let options = vec![
             "Add new subtask",
             "Toggle subtask completion",
             "Remove subtask",
             "Back to main menu",
         ];
-        
+
         let selection = Select::new()
             .with_prompt("Choose an action")
             .items(&options)
             .default(0)
             .interact();
-        
+
         match selection {
             Ok(0) => interactive_add_subtasks(id),
             Ok(1) => interactive_toggle_subtask(id),
             Ok(2) => interactive_remove_subtask(id),
-            Ok(3) | _ => break,
+            _ => break,
+        }
+    }
+}
+
+/// Lets the user pick a subtask of task `id` from an indented list.
+fn select_subtask(id: usize, prompt: &str) -> Option<SubtaskPath> {
+    let data = load_or_exit();
+    let task = match data.find(id) {
+        Some(task) => task,
+        None => {
+            println!("{}", format!("Task with ID {} not found", id).red());
+            return None;
         }
+    };
+
+    if task.subtasks.is_empty() {
+        println!("{}", "This task has no subtasks".yellow());
+        return None;
     }
+
+    let nodes = flatten_subtasks(task);
+    let subtask_names: Vec<String> = nodes
+        .iter()
+        .map(|(path, s)| {
+            format!(
+                "{}{} {}: {}",
+                "  ".repeat(path.len() - 1),
+                format_checkbox(s),
+                subtask_label(path),
+                s.name
+            )
+        })
+        .collect();
+
+    let selection = Select::new()
+        .with_prompt(prompt)
+        .items(&subtask_names)
+        .interact();
+
+    selection.ok().map(|idx| SubtaskPath {
+        task_id: id,
+        indices: nodes[idx].0.clone(),
+    })
 }
 
 pub fn interactive_add_subtasks(id: usize) {
     let mut continue_adding = true;
-    
+
     while continue_adding {
+        let parent = {
+            let data = load_or_exit();
+            let nodes = data.find(id).map(flatten_subtasks).unwrap_or_default();
+
+            let mut parents = vec!["(top level)".to_string()];
+            parents.extend(nodes.iter().map(|(path, s)| {
+                format!("{}{}: {}", "  ".repeat(path.len() - 1), subtask_label(path), s.name)
+            }));
+
+            let selection = if nodes.is_empty() {
+                0
+            } else {
+                Select::new()
+                    .with_prompt("Add under")
+                    .items(&parents)
+                    .default(0)
+                    .interact()
+                    .unwrap_or(0)
+            };
+
+            SubtaskPath {
+                task_id: id,
+                indices: selection.checked_sub(1).map(|i| nodes[i].0.clone()).unwrap_or_default(),
+            }
+        };
+
         let name: String = Input::new()
             .with_prompt("Subtask name")
             .interact_text()
             .unwrap();
-        
-        add_subtask(id, name);
-        
+
+        add_subtask(parent, name, None, None);
+
         continue_adding = Confirm::new()
             .with_prompt("Add another subtask?")
             .default(false)
@@ -231,85 +456,307 @@ pub fn interactive_add_subtasks(id: usize) {
 }
 
 pub fn interactive_toggle_subtask(id: usize) {
-    let tasks = load_tasks();
-    let task = tasks.iter().find(|t| t.id == id);
-    
-    match task {
-        Some(task) => {
-            if task.subtasks.is_empty() {
-                println!("{}", "This task has no subtasks".yellow());
-                return;
-            }
-            
-            let subtask_names: Vec<String> = task.subtasks
-                .iter()
-                .enumerate()
-                .map(|(i, s)| {
-                    let status = if s.completed {
-                        "[✓]".green()
-                    } else {
-                        "[ ]".yellow()
-                    };
-                    format!("{} {}: {}", status, i + 1, s.name)
-                })
-                .collect();
-            
-            let selection = Select::new()
-                .with_prompt("Select subtask to toggle")
-                .items(&subtask_names)
-                .interact();
-            
-            match selection {
-                Ok(idx) => {
-                    toggle_subtask(id, idx);
-                }
-                Err(_) => {}
+    if let Some(path) = select_subtask(id, "Select subtask to toggle") {
+        toggle_subtask(path);
+    }
+}
+
+pub fn interactive_remove_subtask(id: usize) {
+    if let Some(path) = select_subtask(id, "Select subtask to remove") {
+        remove_subtask(path);
+    }
+}
+
+/// Turns the subtask at `path` into a new task inside a transaction,
+/// returning the new task and the completion if its old parent was left
+/// with only checked subtasks.
+fn promote_in(data: &mut TaskData, path: &SubtaskPath) -> Result<(Task, Option<Completion>), StoreError> {
+    let id = path.task_id;
+    let parent = data.find_mut(id).ok_or(StoreError::NotFound(id))?;
+    let before = parent.reopen_state();
+    let subtask = parent
+        .remove_subtask(&path.indices)
+        .ok_or_else(|| subtask_not_found(path))?;
+    let (parent_priority, parent_tags, parent_project) =
+        (parent.priority.clone(), parent.tags.clone(), parent.project.clone());
+    let completion = settle_subtasks_in(data, id, before)?;
+
+    let now = timestamp();
+    let progress = subtask.progress();
+    let mut task = Task {
+        id: 0,
+        uuid: Some(new_uuid()),
+        name: subtask.name,
+        priority: subtask.priority.unwrap_or(parent_priority),
+        status: if subtask.completed { Status::Done } else { Status::Pending },
+        progress,
+        due_date: subtask.due_date,
+        tags: parent_tags,
+        project: parent_project,
+        created_at: now.clone(),
+        completed_at: subtask.completed.then_some(now),
+        subtasks: subtask.subtasks,
+        description: None,
+        annotations: Vec::new(),
+        series: None,
+        depends_on: Vec::new(),
+        parent: Some(id),
+        reopen: None,
+    };
+    task.id = data.allocate_id();
+    data.tasks.push(task.clone());
+    Ok((task, completion))
+}
+
+/// Turns the subtask at `path` into a task of its own, nested subtasks
+/// and all. The new task keeps a link to the task it came from and takes
+/// that task's tags, and its priority unless the subtask had its own.
+pub fn promote_subtask(path: SubtaskPath) {
+    let store = store_or_exit();
+    let id = path.task_id;
+
+    let result = store.modify(|data| promote_in(data, &path));
+
+    match result {
+        Ok((task, completion)) => {
+            println!(
+                "{} {} {}",
+                "✓ Promoted subtask to task:".green().bold(),
+                task.name.bright_white(),
+                format!("(ID {}, from task {})", task.id, id).cyan()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
             }
         }
-        None => {
+        Err(e) => print_store_error("Error promoting subtask:", &e),
+    }
+}
+
+/// Moves task `id` into the subtask tree at `parent` inside a
+/// transaction, returning the subtask's new path and the completion if
+/// the move left the target task with only checked subtasks.
+fn demote_in(
+    data: &mut TaskData,
+    id: usize,
+    parent: &SubtaskPath,
+) -> Result<(SubtaskPath, Option<Completion>), StoreError> {
+    if parent.task_id == id {
+        return Err(StoreError::Rejected("A task can't become its own subtask".to_string()));
+    }
+    let target = data.find(parent.task_id).ok_or(StoreError::NotFound(parent.task_id))?;
+    if !parent.indices.is_empty() && target.subtask(&parent.indices).is_none() {
+        return Err(subtask_not_found(parent));
+    }
+
+    let dependents = crate::deps::dependents(data, id);
+    if !dependents.is_empty() {
+        return Err(StoreError::Rejected(format!(
+            "Tasks {} depend on task {}; remove those dependencies first",
+            crate::deps::format_ids(&dependents),
+            id
+        )));
+    }
+
+    let idx = data.position(id).ok_or(StoreError::NotFound(id))?;
+    if !data.tasks[idx].depends_on.is_empty() {
+        return Err(StoreError::Rejected(format!(
+            "Task {} depends on {}; remove those dependencies first",
+            id,
+            crate::deps::format_ids(&data.tasks[idx].depends_on)
+        )));
+    }
+    let task = data.remove(id).ok_or(StoreError::NotFound(id))?;
+
+    let target = data.find_mut(parent.task_id).ok_or(StoreError::NotFound(parent.task_id))?;
+    let before = target.reopen_state();
+    let subtask = SubTask {
+        id: target.next_subtask_id(),
+        name: task.name,
+        completed: task.status == Status::Done,
+        priority: Some(task.priority),
+        due_date: task.due_date,
+        subtasks: task.subtasks,
+    };
+
+    let siblings = target
+        .subtask_children_mut(&parent.indices)
+        .ok_or_else(|| subtask_not_found(parent))?;
+    siblings.push(subtask);
+
+    let mut path = parent.clone();
+    path.indices.push(siblings.len() - 1);
+    target.assign_subtask_ids();
+    let completion = settle_subtasks_in(data, parent.task_id, before)?;
+    Ok((path, completion))
+}
+
+/// Folds task `id` into the subtask tree at `parent`, keeping its name,
+/// priority, due date, completion and its own subtasks. The task itself
+/// is removed, so anything only a task can hold (tags, project,
+/// description, annotations) is dropped after confirmation.
+pub fn demote_task(id: usize, parent: SubtaskPath) {
+    let store = store_or_exit();
+
+    let task = match store.get(id) {
+        Ok(Some(task)) => task,
+        Ok(None) => {
             println!("{}", format!("Task with ID {} not found", id).red());
+            return;
+        }
+        Err(e) => {
+            print_store_error("Error demoting task:", &e);
+            return;
         }
+    };
+
+    let mut dropped = Vec::new();
+    if !task.tags.is_empty() {
+        dropped.push("tags");
+    }
+    let target_project = store.get(parent.task_id).ok().flatten().and_then(|t| t.project);
+    if task.project.is_some() && task.project != target_project {
+        dropped.push("project");
+    }
+    if task.description.is_some() {
+        dropped.push("description");
+    }
+    if !task.annotations.is_empty() {
+        dropped.push("annotations");
     }
-}
 
-pub fn interactive_remove_subtask(id: usize) {
-    let tasks = load_tasks();
-    let task = tasks.iter().find(|t| t.id == id);
-    
-    match task {
-        Some(task) => {
-            if task.subtasks.is_empty() {
-                println!("{}", "This task has no subtasks".yellow());
-                return;
-            }
-            
-            let subtask_names: Vec<String> = task.subtasks
-                .iter()
-                .enumerate()
-                .map(|(i, s)| {
-                    let status = if s.completed {
-                        "[✓]".green()
-                    } else {
-                        "[ ]".yellow()
-                    };
-                    format!("{} {}: {}", status, i + 1, s.name)
-                })
-                .collect();
-            
-            let selection = Select::new()
-                .with_prompt("Select subtask to remove")
-                .items(&subtask_names)
-                .interact();
-            
-            match selection {
-                Ok(idx) => {
-                    remove_subtask(id, idx);
-                }
-                Err(_) => {}
+    let prompt = if dropped.is_empty() {
+        format!("Move task \"{}\" into {}?", task.name, parent)
+    } else {
+        format!(
+            "Move task \"{}\" into {}? Its {} will be dropped",
+            task.name,
+            parent,
+            dropped.join(", ")
+        )
+    };
+
+    if !Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let result = store.modify(|data| demote_in(data, id, &parent));
+
+    match result {
+        Ok((path, completion)) => {
+            println!(
+                "{} {} {}",
+                "✓ Moved task into subtasks:".green().bold(),
+                task.name.bright_white(),
+                format!("(now {})", path).cyan()
+            );
+            if let Some(completion) = completion {
+                print_completion(completion);
             }
         }
-        None => {
-            println!("{}", format!("Task with ID {} not found", id).red());
+        Err(e) => print_store_error("Error demoting task:", &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::tests::task;
+
+    fn tree() -> Task {
+        let mut design = SubTask::new("Design");
+        design.subtasks = vec![SubTask::new("Sketch"), SubTask::new("Review")];
+        let mut task = task(4, "Launch");
+        task.subtasks = vec![SubTask::new("Brief"), design];
+        task.assign_subtask_ids();
+        task
+    }
+
+    #[test]
+    fn paths_are_one_based_and_dotted() {
+        let path: SubtaskPath = "4.2.1".parse().unwrap();
+        assert_eq!(path, SubtaskPath { task_id: 4, indices: vec![1, 0] });
+        assert_eq!(path.to_string(), "4.2.1");
+        assert_eq!(parse_subtask_path("4").unwrap().indices, Vec::<usize>::new());
+        assert_eq!(path_from_flags(4, "2.1").unwrap(), path);
+        for bad in ["4.0", "4.x", "x.1", ""] {
+            assert!(bad.parse::<SubtaskPath>().is_err(), "{}", bad);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn every_node_has_an_id_and_a_path() {
+        let task = tree();
+        let nodes: Vec<(String, &str, usize)> = flatten_subtasks(&task)
+            .into_iter()
+            .map(|(path, node)| (subtask_label(&path), node.name.as_str(), node.id))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("1".to_string(), "Brief", 1),
+                ("2".to_string(), "Design", 2),
+                ("2.1".to_string(), "Sketch", 3),
+                ("2.2".to_string(), "Review", 4),
+            ]
+        );
+        assert_eq!(task.subtask(&[1, 1]).unwrap().name, "Review");
+        assert!(task.subtask(&[1, 2]).is_none());
+    }
+
+    #[test]
+    fn promoting_keeps_the_parent_s_tags_and_a_link_back() {
+        let mut parent = tree();
+        parent.tags = vec!["launch".to_string()];
+        parent.priority = Priority::from("high".to_string());
+        parent.subtask_mut(&[1, 0]).unwrap().completed = true;
+        let mut data = TaskData::from_legacy(vec![parent]);
+
+        let (task, completion) = promote_in(&mut data, &"4.2".parse().unwrap()).unwrap();
+        assert!(completion.is_none());
+        assert_eq!((task.id, task.name.as_str(), task.parent), (5, "Design", Some(4)));
+        assert_eq!((task.priority.as_str(), task.tags.clone()), ("high", vec!["launch".to_string()]));
+        assert_eq!((task.subtasks.len(), task.progress), (2, 50));
+        assert_eq!(data.find(4).unwrap().subtasks.len(), 1);
+        assert!(promote_in(&mut data, &"4.3".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn demoting_keeps_completion_and_refuses_bad_targets() {
+        let mut done = task(7, "Pick a date");
+        done.status = Status::Done;
+        let mut data = TaskData::from_legacy(vec![tree(), done]);
+
+        assert!(demote_in(&mut data, 7, &"7".parse().unwrap()).is_err());
+        assert!(demote_in(&mut data, 7, &"4.9".parse().unwrap()).is_err());
+        assert!(data.find(7).is_some());
+
+        let (path, _) = demote_in(&mut data, 7, &"4.2".parse().unwrap()).unwrap();
+        assert_eq!(path.to_string(), "4.2.3");
+        assert!(data.find(7).is_none());
+        let launch = data.find(4).unwrap();
+        let moved = launch.subtask(&path.indices).unwrap();
+        assert_eq!((moved.name.as_str(), moved.completed, moved.id), ("Pick a date", true, 5));
+        assert_eq!(launch.subtask(&[1]).unwrap().progress(), 33);
+        assert_eq!(launch.progress, 16);
+    }
+
+    #[test]
+    fn progress_rolls_up_the_tree() {
+        let mut task = tree();
+        task.subtask_mut(&[1, 0]).unwrap().completed = true;
+        task.roll_up_subtasks();
+        assert_eq!(task.subtask(&[1]).unwrap().progress(), 50);
+        assert_eq!(task.progress, 25);
+
+        task.subtask_mut(&[1, 1]).unwrap().completed = true;
+        task.roll_up_subtasks();
+        assert!(task.subtask(&[1]).unwrap().completed);
+        assert_eq!(task.progress, 50);
+    }
+}
@@ -0,0 +1,111 @@
+use colored::*;
+use std::collections::HashMap;
+
+use crate::commands::{load_or_exit, print_store_error, store_or_exit};
+use crate::store::StoreError;
+
+/// Prints every tag with how many tasks carry it, most used first.
+pub fn show_tags() {
+    let data = load_or_exit();
+
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for task in &data.tasks {
+        for tag in &task.tags {
+            let entry = counts.entry(tag.as_str()).or_default();
+            entry.0 += 1;
+            if !task.status.is_closed() {
+                entry.1 += 1;
+            }
+        }
+    }
+    if counts.is_empty() {
+        println!("{}", "No tags yet".yellow());
+        return;
+    }
+
+    let mut tags: Vec<(&str, (usize, usize))> = counts.into_iter().collect();
+    tags.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(b.0)));
+    let width = tags.iter().map(|(tag, _)| tag.chars().count() + 1).max().unwrap_or(0);
+
+    println!();
+    for (tag, (total, open)) in tags {
+        println!(
+            "{}  {:>4} tasks  {}",
+            format!("{:<width$}", format!("#{}", tag), width = width).bright_blue(),
+            total,
+            format!("({} open)", open).bright_black()
+        );
+    }
+    println!();
+}
+
+/// Replaces every tag in `sources` with `target` on one task, keeping the
+/// place of the first one. Returns whether anything changed.
+fn retag(tags: &mut Vec<String>, sources: &[String], target: &str) -> bool {
+    let Some(first) = tags.iter().position(|t| sources.contains(t)) else {
+        return false;
+    };
+    let before = tags.clone();
+    tags[first] = target.to_string();
+    let mut seen = Vec::new();
+    tags.retain(|t| {
+        let keep = !sources.contains(t) && !seen.contains(t);
+        if keep {
+            seen.push(t.clone());
+        }
+        keep
+    });
+    *tags != before
+}
+
+/// Renames tag `from` to `to` on every task and recurring series. If some
+/// tasks already have `to`, the two tags are merged.
+pub fn rename_tag(from: String, to: String) {
+    merge_tags(vec![from], to);
+}
+
+/// Replaces each tag in `sources` with `target` on every task and
+/// recurring series, so they become one tag.
+pub fn merge_tags(sources: Vec<String>, target: String) {
+    let clean = |tag: &str| tag.trim().trim_start_matches('#').to_string();
+    let target = clean(&target);
+    let sources: Vec<String> = sources.iter().map(|t| clean(t)).filter(|t| *t != target).collect();
+    if target.is_empty() || target.contains(',') || target.contains(char::is_whitespace) {
+        println!("{}", format!("Invalid tag '{}'", target).red().bold());
+        return;
+    }
+    if sources.is_empty() {
+        println!("{}", "Nothing to change".yellow());
+        return;
+    }
+
+    let store = store_or_exit();
+    let result = store.modify(|data| {
+        let mut count = 0;
+        for task in &mut data.tasks {
+            if retag(&mut task.tags, &sources, &target) {
+                count += 1;
+            }
+        }
+        for series in &mut data.series {
+            retag(&mut series.tags, &sources, &target);
+        }
+        Ok::<_, StoreError>(count)
+    });
+
+    let names = sources.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(", ");
+    match result {
+        Ok(0) => {
+            println!("{}", format!("No task is tagged {}", names).yellow());
+        }
+        Ok(count) => {
+            println!(
+                "{} {} {}",
+                format!("✓ Retagged {} tasks:", count).green().bold(),
+                names.bright_white(),
+                format!("→ #{}", target).bright_blue()
+            );
+        }
+        Err(e) => print_store_error("Error renaming tags:", &e),
+    }
+}
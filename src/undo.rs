@@ -0,0 +1,112 @@
+use colored::*;
+use dialoguer::Confirm;
+
+use crate::commands::print_store_error;
+use crate::store::journal::{Direction, JournalEntry};
+use crate::store::open_journaled;
+
+pub fn undo() {
+    step(Direction::Undo);
+}
+
+pub fn redo() {
+    step(Direction::Redo);
+}
+
+/// Shows what the next undo or redo would change, and applies it once
+/// confirmed.
+fn step(direction: Direction) {
+    let store = match open_journaled() {
+        Ok(store) => store,
+        Err(e) => {
+            println!("{} {}", "Error opening task store:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let journal = match store.journal() {
+        Ok(journal) => journal,
+        Err(e) => {
+            print_store_error("Error reading the journal:", &e);
+            return;
+        }
+    };
+
+    let Some(entry) = journal.next(direction) else {
+        println!("{}", format!("Nothing to {}", direction).yellow());
+        return;
+    };
+
+    print_entry(entry, direction);
+
+    let verb = match direction {
+        Direction::Undo => "Undo",
+        Direction::Redo => "Redo",
+    };
+    if !Confirm::new()
+        .with_prompt(format!("{} this?", verb))
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    match store.step(direction, entry.seq) {
+        Ok(entry) => {
+            let done = match direction {
+                Direction::Undo => "✓ Undid:",
+                Direction::Redo => "✓ Redid:",
+            };
+            println!("{} {}", done.green().bold(), entry.command.bright_white());
+        }
+        Err(e) => print_store_error(&format!("Error during {}:", direction), &e),
+    }
+}
+
+fn print_entry(entry: &JournalEntry, direction: Direction) {
+    println!(
+        "\n{} {} {}",
+        format!("{}:", direction).cyan().bold(),
+        entry.command.bright_white(),
+        format!("({})", entry.at).cyan()
+    );
+
+    for change in &entry.changes {
+        let line = match (&change.before, &change.after, direction) {
+            (None, Some(task), Direction::Undo) => {
+                format!("remove added task [{}] {}", task.id, task.name).red()
+            }
+            (None, Some(task), Direction::Redo) => {
+                format!("add task [{}] {}", task.id, task.name).green()
+            }
+            (Some(task), None, Direction::Undo) => {
+                format!("restore removed task [{}] {}", task.id, task.name).green()
+            }
+            (Some(task), None, Direction::Redo) => {
+                format!("remove task [{}] {}", task.id, task.name).red()
+            }
+            (Some(_), Some(task), _) => {
+                let verb = match direction {
+                    Direction::Undo => "revert",
+                    Direction::Redo => "reapply",
+                };
+                format!(
+                    "{} task [{}] {}: {}",
+                    verb,
+                    task.id,
+                    task.name,
+                    change.changed_fields().join(", ")
+                )
+                .yellow()
+            }
+            (None, None, _) => continue,
+        };
+        println!("  • {}", line);
+    }
+
+    if entry.series.is_some() {
+        println!("  • {}", "recurring series settings".yellow());
+    }
+    println!();
+}